@@ -0,0 +1,72 @@
+// Compares the word search against the trigonometric offsets it used to compute per cell.
+// Run with `cargo bench --bench day04_directions`
+#![allow(dead_code, unused_imports)]
+
+use std::f32::consts::PI;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+#[path = "../src/bin/day04/direction.rs"]
+mod direction;
+#[path = "../src/bin/day04/word_search.rs"]
+mod word_search;
+#[cfg(test)]
+#[path = "../src/bin/day04/test_support.rs"]
+mod test_support;
+
+use direction::Direction;
+use word_search::WordSearch;
+
+/// A square grid of X, M, A and S from a fixed seed, so every run searches the same letters
+fn grid(size: usize) -> Vec<Vec<char>> {
+    let mut seed: u64 = 0x2024_0004;
+    (0..size).map(|_| (0..size).map(|_| {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ['X', 'M', 'A', 'S'][(seed >> 62) as usize]
+    }).collect()).collect()
+}
+
+fn trigonometric_offset(direction: u8) -> (i32, i32) {
+    let x = ((f32::from(direction) * PI / 4f32).cos().round()) as i32;
+    let y = ((f32::from(direction) * PI / 4f32).sin().round()) as i32;
+    (x, y)
+}
+
+fn count_xmas(grid: &[Vec<char>], offset: impl Fn(usize) -> (i32, i32)) -> usize {
+    let word = ['X', 'M', 'A', 'S'];
+    let mut count = 0;
+    for y in 0..grid.len() {
+        for x in 0..grid[y].len() {
+            for direction in 0..8 {
+                count += word.iter().enumerate().all(|(index, char)| {
+                    let (x_offset, y_offset) = offset(direction);
+                    let x = x as i32 + index as i32 * x_offset;
+                    let y = y as i32 + index as i32 * y_offset;
+                    y >= 0 && (y as usize) < grid.len() && x >= 0 && (x as usize) < grid[y as usize].len()
+                        && grid[y as usize][x as usize] == *char
+                }) as usize;
+            }
+        }
+    }
+    count
+}
+
+fn directions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("count_xmas");
+    for size in [140, 1_000] {
+        let grid = grid(size);
+        group.bench_with_input(BenchmarkId::new("trigonometric", size), &grid, |b, grid| {
+            b.iter(|| count_xmas(black_box(grid), |direction| trigonometric_offset(direction as u8)))
+        });
+        group.bench_with_input(BenchmarkId::new("direction_table", size), &grid, |b, grid| {
+            b.iter(|| count_xmas(black_box(grid), |direction| Direction::ALL[direction].offset()))
+        });
+        let search = WordSearch::new(&["XMAS"]);
+        group.bench_with_input(BenchmarkId::new("word_search", size), &grid, |b, grid| {
+            b.iter(|| search.find_all(black_box(grid)).len())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, directions);
+criterion_main!(benches);
@@ -0,0 +1,88 @@
+// Compares checking updates against the rule index with the check it replaced, which kept a set of
+// printed pages per update and scanned each page's rules. Run with `cargo bench --bench day05_validation`
+#![allow(dead_code, unused_imports)]
+
+use std::collections::{HashMap, HashSet};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[path = "../src/bin/day05/rule_graph.rs"]
+mod rule_graph;
+#[path = "../src/bin/day05/rule_index.rs"]
+mod rule_index;
+
+use rule_graph::RuleGraph;
+use rule_index::RuleIndex;
+
+const PAGES: usize = 400;
+const UPDATES: usize = 5_000;
+
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.0 >> 33) % bound as u64) as usize
+    }
+}
+
+/**
+ * Rules ordering most pairs of pages by a hidden order, and updates of 100 to 300 distinct pages.
+ * Every other update is put in the hidden order so about half of them are valid.
+ */
+fn puzzle() -> (HashMap<usize, Vec<usize>>, Vec<Vec<usize>>) {
+    let mut random = Random(20_240_005);
+    let mut rules: HashMap<usize, Vec<usize>> = HashMap::new();
+    for before in 0..PAGES {
+        for after in before + 1..PAGES {
+            if random.below(4) != 0 {
+                rules.entry(before).or_default().push(after);
+            }
+        }
+    }
+    let updates = (0..UPDATES).map(|update| {
+        let length = 100 + random.below(201);
+        let mut pages: Vec<usize> = (0..PAGES).collect();
+        for index in 0..length {
+            let other = index + random.below(PAGES - index);
+            pages.swap(index, other);
+        }
+        pages.truncate(length);
+        if update % 2 == 0 {
+            pages.sort();
+        }
+        pages
+    }).collect();
+    (rules, updates)
+}
+
+fn is_ordered_by_scanning(rules: &HashMap<usize, Vec<usize>>, update: &[usize]) -> bool {
+    let mut visited: HashSet<usize> = HashSet::new();
+    !update.iter().any(|page| {
+        if rules.get(page).is_some_and(|afters| afters.iter().any(|after| visited.contains(after))) {
+            return true;
+        }
+        visited.insert(*page);
+        false
+    })
+}
+
+fn validation(c: &mut Criterion) {
+    let (rules, updates) = puzzle();
+    let index = RuleIndex::from(&RuleGraph::from(&rules));
+    let scanned: Vec<bool> = updates.iter().map(|update| is_ordered_by_scanning(&rules, update)).collect();
+    let indexed: Vec<bool> = updates.iter().map(|update| index.is_ordered(update)).collect();
+    assert_eq!(scanned, indexed);
+
+    let mut group = c.benchmark_group("validate_updates");
+    group.sample_size(10);
+    group.bench_function("scanning", |b| {
+        b.iter(|| updates.iter().filter(|update| is_ordered_by_scanning(black_box(&rules), update)).count())
+    });
+    group.bench_function("rule_index", |b| {
+        b.iter(|| updates.iter().filter(|update| black_box(&index).is_ordered(update)).count())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, validation);
+criterion_main!(benches);
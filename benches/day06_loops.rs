@@ -0,0 +1,33 @@
+// Compares counting loop obstacles with jump tables against replaying the whole patrol a cell at
+// a time for every candidate, which is how it used to be done. Run with `cargo bench --bench day06_loops`
+#![allow(dead_code, unused_imports)]
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+#[path = "../src/bin/day06/area.rs"]
+mod area;
+#[path = "../src/bin/day06/jump_table.rs"]
+mod jump_table;
+#[path = "../src/bin/day06/patrol_rules.rs"]
+mod patrol_rules;
+
+use area::Area;
+use jump_table::JumpTable;
+
+fn loops(c: &mut Criterion) {
+    // The puzzle input is a 130 by 130 map
+    let mut area = Area::from(std::fs::read_to_string("./src/bin/day06/input.txt").unwrap());
+    let guard = area.guards[0];
+    assert_eq!(area.loop_obstacles_by_replaying(&guard).len(), area.how_many_unique_new_obstacles_cause_a_loop());
+
+    let mut group = c.benchmark_group("loop_obstacles");
+    group.sample_size(10);
+    group.bench_function("replaying", |b| b.iter(|| black_box(&mut area).loop_obstacles_by_replaying(&guard).len()));
+    group.bench_function("jump_table", |b| {
+        b.iter(|| JumpTable::new(black_box(&area)).loop_obstacles(&area, &area.guards[0]).len())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, loops);
+criterion_main!(benches);
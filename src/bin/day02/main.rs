@@ -1,14 +1,73 @@
+use std::fs::File;
+use std::io::{stdin, BufWriter, Write};
+use std::ops::Range;
+use safety_rule::SafetyRule;
+use stream::ReportOutputs;
+
+mod safety_rule;
+mod stream;
+mod verdict;
+
+/// How many reports go by between progress updates when streaming
+const PROGRESS_INTERVAL: usize = 10_000;
+
+/// How many levels the Problem Dampener can remove from a report
+const DAMPENER_TOLERANCE: usize = 1;
 
 fn main() {
+    // `--verdicts` prints how stable each report is and why it is or isn't safe. `--stream`
+    // validates reports from stdin instead, copying them to the files given by `--safe=<path>`,
+    // `--dampened=<path>` for those only safe with the dampener, and `--unsafe=<path>`. Any
+    // other arguments form a rule such as `steps=1..=3
+    // direction=either` that replaces the default safety rule
+    let (flags, settings): (Vec<String>, Vec<String>) = std::env::args().skip(1)
+        .partition(|argument| argument.starts_with("--"));
+    let rule = SafetyRule::from(settings.join(" ").as_str());
+    if flags.iter().any(|flag| flag == "--stream") {
+        stream_reports(&flags, &rule);
+        return;
+    }
     let reports = parse_input("./src/bin/day02/input.txt");
+    if flags.iter().any(|flag| flag == "--verdicts") {
+        print!("{}", verdict_table(&reports, &rule));
+    }
     // Part 1
-    let counts = count_safe_reports(&reports);
+    let counts = count_safe_reports(&reports, &rule);
     println!("{}", counts);
     // Part 2
-    let counts_with_dampener = count_safe_reports_dampened(&reports);
+    let counts_with_dampener = count_safe_reports_dampened(&reports, &rule);
     println!("{}", counts_with_dampener);
 }
 
+fn stream_reports(flags: &[String], rule: &SafetyRule) {
+    let output_file = |name: &str| -> Option<BufWriter<File>> {
+        flags.iter()
+            .find_map(|flag| flag.strip_prefix(name))
+            .map(|path| BufWriter::new(File::create(path).unwrap_or_else(|_| panic!("Error creating file path {}", path))))
+    };
+    let mut safe_output = output_file("--safe=");
+    let mut dampened_output = output_file("--dampened=");
+    let mut unsafe_output = output_file("--unsafe=");
+    let outputs = ReportOutputs {
+        safe: safe_output.as_mut().map(|output| output as &mut dyn Write),
+        dampened: dampened_output.as_mut().map(|output| output as &mut dyn Write),
+        unsafe_reports: unsafe_output.as_mut().map(|output| output as &mut dyn Write)
+    };
+    let counts = stream::filter_reports(
+        stdin().lock(),
+        rule,
+        DAMPENER_TOLERANCE,
+        outputs,
+        |counts| if counts.reports % PROGRESS_INTERVAL == 0 {
+            eprintln!("{} reports, {} safe, {} safe with dampener", counts.reports, counts.safe, counts.safe_dampened);
+        }
+    ).unwrap_or_else(|error| panic!("Error streaming reports: {}", error));
+    // Part 1
+    println!("{}", counts.safe);
+    // Part 2
+    println!("{}", counts.safe_dampened);
+}
+
 fn parse_input(file_path: &str) -> Vec<Vec<i32>> {
     let input_string = std::fs::read_to_string(file_path)
         .expect(&format!("Error reading file path {}", file_path));
@@ -20,30 +79,50 @@ fn parse_input(file_path: &str) -> Vec<Vec<i32>> {
     .collect()
 }
 
-fn count_safe_reports(reports: &Vec<Vec<i32>>) -> usize {
-    reports.iter().filter(|report| is_safe(report)).count()
+fn count_safe_reports(reports: &[Vec<i32>], rule: &SafetyRule) -> usize {
+    reports.iter().filter(|report| is_safe(report, rule)).count()
 }
 
-/**
- * Sliding window technique to determine if each level transition is safe.
- */
-fn is_safe(report: &Vec<i32>) -> bool {
-    let differences: Vec<i32> = report.windows(2).map(|window| window[0] - window[1]).collect();
-    (differences.iter().all(|difference| difference.is_positive() && (1..=3).contains(difference))) ||
-    (differences.iter().all(|difference| difference.is_negative() && (-3..=-1).contains(difference)))
+fn is_safe(report: &[i32], rule: &SafetyRule) -> bool {
+    rule.is_safe(report)
+}
+
+fn count_safe_reports_dampened(reports: &[Vec<i32>], rule: &SafetyRule) -> usize {
+    reports.iter().filter(|report| is_safe_dampened(report, rule)).count()
+}
+
+fn is_safe_dampened(report: &[i32], rule: &SafetyRule) -> bool {
+    rule.removals(report, DAMPENER_TOLERANCE).is_some()
 }
 
-fn count_safe_reports_dampened(reports: &Vec<Vec<i32>>) -> usize {
-    reports.iter().filter(|report| is_safe_dampened(report)).count()
+/**
+ * How far from safe a report is: the longest stretch of it that is already safe, and the
+ * fewest levels that would have to be removed to make all of it safe.
+ */
+fn stability(report: &[i32], rule: &SafetyRule) -> (Range<usize>, usize) {
+    (rule.longest_safe_run(report), rule.min_removals(report))
 }
 
-fn is_safe_dampened(report: &Vec<i32>) -> bool {
-    report.iter().enumerate().fold(Vec::<Vec<i32>>::new(), |mut acc, (index, _)| {
-        let mut report_iteration = report.clone();
-        report_iteration.remove(index);
-        acc.push(report_iteration);
-        acc
-    }).iter().any(|iteration| is_safe(iteration))
+/**
+ * One row per report with how stable it is and its dampened verdict, lined up so the levels
+ * column is easy to scan.
+ */
+fn verdict_table(reports: &[Vec<i32>], rule: &SafetyRule) -> String {
+    let rows: Vec<(String, String)> = reports.iter()
+        .map(|report| {
+            let levels = report.iter().map(|level| level.to_string()).collect::<Vec<_>>().join(" ");
+            let (run, removals) = stability(report, rule);
+            let details = format!("{:<8} | {:>8} | {}", format!("{:?}", run), removals, rule.verdict(report, DAMPENER_TOLERANCE));
+            (levels, details)
+        })
+        .collect();
+    let width = rows.iter().map(|(levels, _)| levels.len()).max().unwrap_or(0).max("Levels".len());
+    let number_width = reports.len().to_string().len().max("Report".len());
+    let mut table = format!("{:>number_width$} | {:<width$} | Safe run | Removals | Verdict\n", "Report", "Levels");
+    rows.iter().enumerate().for_each(|(index, (levels, details))| {
+        table.push_str(&format!("{:>number_width$} | {:<width$} | {}\n", index + 1, levels, details));
+    });
+    table
 }
 
 #[cfg(test)]
@@ -72,13 +151,14 @@ mod tests {
         let report4 = vec![1, 3, 2, 4, 5];
         let report5 = vec![8, 6, 4, 4, 1];
         let report6 = vec![1, 3, 6, 7, 9];
-
-        assert_eq!(is_safe(&report1), true, "Report 1");
-        assert_eq!(is_safe(&report2), false, "Report 2");
-        assert_eq!(is_safe(&report3), false, "Report 3");
-        assert_eq!(is_safe(&report4), false, "Report 4");
-        assert_eq!(is_safe(&report5), false, "Report 5");
-        assert_eq!(is_safe(&report6), true, "Report 6");
+        let rule = SafetyRule::default();
+
+        assert_eq!(is_safe(&report1, &rule), true, "Report 1");
+        assert_eq!(is_safe(&report2, &rule), false, "Report 2");
+        assert_eq!(is_safe(&report3, &rule), false, "Report 3");
+        assert_eq!(is_safe(&report4, &rule), false, "Report 4");
+        assert_eq!(is_safe(&report5, &rule), false, "Report 5");
+        assert_eq!(is_safe(&report6, &rule), true, "Report 6");
     }
 
     #[test]
@@ -92,7 +172,7 @@ mod tests {
             vec![1, 3, 6, 7, 9]
         ];
 
-        assert_eq!(count_safe_reports(&reports), 2);
+        assert_eq!(count_safe_reports(&reports, &SafetyRule::default()), 2);
     }
 
     #[test]
@@ -103,13 +183,14 @@ mod tests {
         let report4 = vec![1, 3, 2, 4, 5];
         let report5 = vec![8, 6, 4, 4, 1];
         let report6 = vec![1, 3, 6, 7, 9];
-
-        assert_eq!(is_safe_dampened(&report1), true, "Report 1");
-        assert_eq!(is_safe_dampened(&report2), false, "Report 2");
-        assert_eq!(is_safe_dampened(&report3), false, "Report 3");
-        assert_eq!(is_safe_dampened(&report4), true, "Report 4");
-        assert_eq!(is_safe_dampened(&report5), true, "Report 5");
-        assert_eq!(is_safe_dampened(&report6), true, "Report 6");
+        let rule = SafetyRule::default();
+
+        assert_eq!(is_safe_dampened(&report1, &rule), true, "Report 1");
+        assert_eq!(is_safe_dampened(&report2, &rule), false, "Report 2");
+        assert_eq!(is_safe_dampened(&report3, &rule), false, "Report 3");
+        assert_eq!(is_safe_dampened(&report4, &rule), true, "Report 4");
+        assert_eq!(is_safe_dampened(&report5, &rule), true, "Report 5");
+        assert_eq!(is_safe_dampened(&report6, &rule), true, "Report 6");
     }
 
     #[test]
@@ -123,6 +204,38 @@ mod tests {
             vec![1, 3, 6, 7, 9]
         ];
 
-        assert_eq!(count_safe_reports_dampened(&reports), 4);
+        assert_eq!(count_safe_reports_dampened(&reports, &SafetyRule::default()), 4);
+    }
+
+    #[test]
+    fn test_verdict_table() {
+        let reports = vec![
+            vec![7, 6, 4, 2, 1],
+            vec![1, 2, 7, 8, 9],
+            vec![1, 3, 2, 4, 5]
+        ];
+        let expected = "\
+Report | Levels    | Safe run | Removals | Verdict
+     1 | 7 6 4 2 1 | 0..5     |        0 | safe
+     2 | 1 2 7 8 9 | 2..5     |        2 | unsafe (step too large at index 2)
+     3 | 1 3 2 4 5 | 2..5     |        1 | safe after removing [2] (direction change at index 2)
+";
+        assert_eq!(verdict_table(&reports, &SafetyRule::default()), expected);
+    }
+
+    #[test]
+    fn test_streamed_files_match_parts() {
+        // Streaming with the dampener's tolerance, the safe file holds Part 1's reports and the
+        // dampened file the ones Part 2 adds
+        let input = std::fs::read_to_string("./src/bin/day02/sample_input.txt").unwrap();
+        let reports = parse_input("./src/bin/day02/sample_input.txt");
+        let rule = SafetyRule::default();
+        let (mut safe, mut dampened) = (Vec::new(), Vec::new());
+        let outputs = ReportOutputs { safe: Some(&mut safe), dampened: Some(&mut dampened), unsafe_reports: None };
+        let counts = stream::filter_reports(input.as_bytes(), &rule, DAMPENER_TOLERANCE, outputs, |_| {}).unwrap();
+        let safe_lines = String::from_utf8(safe).unwrap().lines().count();
+        let dampened_lines = String::from_utf8(dampened).unwrap().lines().count();
+        assert_eq!((counts.safe, safe_lines), (count_safe_reports(&reports, &rule), 2));
+        assert_eq!((counts.safe_dampened, safe_lines + dampened_lines), (count_safe_reports_dampened(&reports, &rule), 4));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,526 @@
+use std::ops::{Range, RangeInclusive};
+use crate::verdict::{Verdict, Violation, ViolationKind};
+
+/// Removals needed before a kept level, and the previously kept level and its plateau length
+type KeptLevel = Option<(usize, Option<(usize, usize)>)>;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Monotonicity {
+    Increasing,
+    Decreasing,
+    Either,
+    Unrestricted
+}
+
+impl Monotonicity {
+    /**
+     * The signs a report's steps may take. A report only needs to satisfy one of them, and
+     * `0` means a step may go either way.
+     */
+    fn signs(&self) -> &'static [i64] {
+        match self {
+            Monotonicity::Increasing => &[1],
+            Monotonicity::Decreasing => &[-1],
+            Monotonicity::Either => &[1, -1],
+            Monotonicity::Unrestricted => &[0]
+        }
+    }
+}
+
+impl From<&str> for Monotonicity {
+    fn from(value: &str) -> Self {
+        match value {
+            "increasing" => Monotonicity::Increasing,
+            "decreasing" => Monotonicity::Decreasing,
+            "either" => Monotonicity::Either,
+            "none" | "any" => Monotonicity::Unrestricted,
+            _ => panic!("Invalid rule. {} is not a direction.", value)
+        }
+    }
+}
+
+/**
+ * Describes what makes a report safe.
+ *
+ * `steps` bounds the size of each change between adjacent levels, ignoring its sign.
+ * Repeated levels are not steps; instead `max_plateau` is the number of times in a row a
+ * level may repeat. `bounds`, when set, is the range every level must fall within.
+ */
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct SafetyRule {
+    pub steps: RangeInclusive<u64>,
+    pub direction: Monotonicity,
+    pub max_plateau: usize,
+    pub bounds: Option<RangeInclusive<i32>>
+}
+
+impl Default for SafetyRule {
+    fn default() -> Self {
+        Self {
+            steps: 1..=3,
+            direction: Monotonicity::Either,
+            max_plateau: 0,
+            bounds: None
+        }
+    }
+}
+
+impl SafetyRule {
+    pub fn is_safe(&self, report: &[i32]) -> bool {
+        self.direction.signs().iter().any(|sign| {
+            report.iter().all(|level| self.allows_level(*level)) &&
+            report.windows(2)
+                .try_fold(0, |plateau, window| self.next_plateau(window[0], window[1], *sign, plateau))
+                .is_some()
+        })
+    }
+
+    /**
+     * Finds the fewest levels that can be removed to make a report safe, as long as no more
+     * than `tolerance` are needed. Returns the indices of the removed levels, or `None` if the
+     * report cannot be made safe within the tolerance.
+     */
+    pub fn removals(&self, report: &[i32], tolerance: usize) -> Option<Vec<usize>> {
+        self.direction.signs().iter()
+            .filter_map(|sign| self.removals_with_sign(report, tolerance, *sign))
+            .min_by_key(|removals| removals.len())
+    }
+
+    /**
+     * Finds the first violation of the rule in a report, or `None` if it is safe. When either
+     * direction is allowed, the first change between levels decides which one the report takes.
+     */
+    pub fn diagnose(&self, report: &[i32]) -> Option<Violation> {
+        let mut sign = match self.direction {
+            Monotonicity::Either => None,
+            direction => Some(direction.signs()[0])
+        };
+        let mut plateau = 0;
+        report.iter().enumerate().find_map(|(index, level)| {
+            if !self.allows_level(*level) {
+                return Some(Violation { index, kind: ViolationKind::OutOfBounds });
+            }
+            let previous = report.get(index.checked_sub(1)?)?;
+            let difference = *level as i64 - *previous as i64;
+            let kind = if difference == 0 {
+                plateau += 1;
+                (plateau > self.max_plateau).then_some(ViolationKind::ZeroStep)
+            } else {
+                plateau = 0;
+                let expected = *sign.get_or_insert(difference.signum());
+                if expected != 0 && difference.signum() != expected {
+                    Some(ViolationKind::DirectionChange)
+                } else if difference.unsigned_abs() > *self.steps.end() {
+                    Some(ViolationKind::StepTooLarge)
+                } else if difference.unsigned_abs() < *self.steps.start() {
+                    Some(ViolationKind::StepTooSmall)
+                } else {
+                    None
+                }
+            };
+            kind.map(|kind| Violation { index, kind })
+        })
+    }
+
+    /**
+     * Diagnoses a report, noting which levels to remove if it can be made safe by removing no
+     * more than `tolerance` of them.
+     */
+    pub fn verdict(&self, report: &[i32], tolerance: usize) -> Verdict {
+        match self.diagnose(report) {
+            None => Verdict::Safe,
+            Some(violation) => match self.removals(report, tolerance) {
+                Some(removals) => Verdict::Dampened { violation, removals },
+                None => Verdict::Unsafe(violation)
+            }
+        }
+    }
+
+    /**
+     * Finds the longest contiguous run of levels that is safe on its own. Ties go to the
+     * earliest run.
+     */
+    pub fn longest_safe_run(&self, report: &[i32]) -> Range<usize> {
+        self.direction.signs().iter()
+            .map(|sign| self.longest_safe_run_with_sign(report, *sign))
+            .fold(0..0, |longest, run| if run.len() > longest.len() { run } else { longest })
+    }
+
+    /**
+     * The fewest levels that have to be removed for the report to be safe. This is the
+     * dampened search with no limit on removals, so it runs in O(n^2 * max_plateau).
+     */
+    pub fn min_removals(&self, report: &[i32]) -> usize {
+        self.removals(report, report.len())
+            .map(|removals| removals.len())
+            .unwrap_or(report.len())
+    }
+
+    fn allows_level(&self, level: i32) -> bool {
+        self.bounds.as_ref().is_none_or(|bounds| bounds.contains(&level))
+    }
+
+    /**
+     * Checks the step between two kept levels, returning the length of the plateau after it
+     * or `None` if the step is not allowed.
+     */
+    fn next_plateau(&self, from: i32, to: i32, sign: i64, plateau: usize) -> Option<usize> {
+        let difference = to as i64 - from as i64;
+        if difference == 0 {
+            return (plateau < self.max_plateau).then_some(plateau + 1);
+        }
+        let direction_allowed = sign == 0 || difference.signum() == sign;
+        (direction_allowed && self.steps.contains(&difference.unsigned_abs())).then_some(0)
+    }
+
+    /**
+     * Grows a run one level at a time. When a step breaks the rule the run restarts at the
+     * level it leads to, except for a plateau that grew too long, which keeps as much of the
+     * plateau as the rule allows.
+     */
+    fn longest_safe_run_with_sign(&self, report: &[i32], sign: i64) -> Range<usize> {
+        let mut longest = 0..0;
+        let mut start = 0;
+        let mut plateau = 0;
+        for (index, level) in report.iter().enumerate() {
+            if !self.allows_level(*level) {
+                start = index + 1;
+                plateau = 0;
+                continue;
+            }
+            if index > start {
+                match self.next_plateau(report[index - 1], *level, sign, plateau) {
+                    Some(next) => plateau = next,
+                    None if *level == report[index - 1] => start = index - self.max_plateau,
+                    None => {
+                        start = index;
+                        plateau = 0;
+                    }
+                }
+            }
+            if index + 1 - start > longest.len() {
+                longest = start..index + 1;
+            }
+        }
+        longest
+    }
+
+    /**
+     * Single pass over the report keeping, for each level and plateau length, the fewest
+     * removals needed before it when that level is kept. Since at most `tolerance` levels can be
+     * skipped, the previously kept level is always one of the `tolerance + 1` levels before it,
+     * so this runs in O(n * tolerance * max_plateau) without ever cloning the report.
+     */
+    fn removals_with_sign(&self, report: &[i32], tolerance: usize, sign: i64) -> Option<Vec<usize>> {
+        let length = report.len();
+        if length == 0 {
+            return Some(Vec::new());
+        }
+        let window = tolerance.saturating_add(1);
+        let plateaus = self.max_plateau.min(length) + 1;
+        let mut best: Vec<Vec<KeptLevel>> = Vec::with_capacity(length);
+        for index in 0..length {
+            let mut candidates = vec![None; plateaus];
+            if self.allows_level(report[index]) {
+                if index <= tolerance {
+                    candidates[0] = Some((index, None));
+                }
+                for previous in index.saturating_sub(window)..index {
+                    for (plateau, state) in best[previous].iter().enumerate() {
+                        let Some((removed, _)) = state else { continue };
+                        let removed = removed + (index - previous - 1);
+                        if removed > tolerance {
+                            continue;
+                        }
+                        let Some(next) = self.next_plateau(report[previous], report[index], sign, plateau) else { continue };
+                        if candidates[next].is_none_or(|(current, _)| removed < current) {
+                            candidates[next] = Some((removed, Some((previous, plateau))));
+                        }
+                    }
+                }
+            }
+            best.push(candidates);
+        }
+
+        let last = (length.saturating_sub(window)..length)
+            .flat_map(|index| {
+                best[index].iter().enumerate()
+                    .filter_map(move |(plateau, state)| state.map(|(removed, _)| ((index, plateau), removed + (length - 1 - index))))
+            })
+            .filter(|(_, removed)| *removed <= tolerance)
+            .min_by_key(|(_, removed)| *removed);
+        let Some((last, _)) = last else {
+            // Removing every level leaves an empty report, which is always safe
+            return (length <= tolerance).then(|| (0..length).collect());
+        };
+
+        let mut kept = vec![false; length];
+        let mut current = Some(last);
+        while let Some((index, plateau)) = current {
+            kept[index] = true;
+            current = best[index][plateau].and_then(|(_, previous)| previous);
+        }
+        Some((0..length).filter(|index| !kept[*index]).collect())
+    }
+}
+
+/**
+ * Parses a rule from whitespace or comma separated `key=value` settings. Any setting left out
+ * keeps its default, e.g. `steps=1..=5 direction=increasing plateau=1 bounds=0..=100`.
+ */
+impl From<&str> for SafetyRule {
+    fn from(value: &str) -> Self {
+        fn parse_range<T: std::str::FromStr>(value: &str) -> RangeInclusive<T> {
+            let (start, end) = value.split_once("..=")
+                .unwrap_or_else(|| panic!("Invalid rule. {} is not a range like 1..=3.", value));
+            let parse = |bound: &str| bound.parse::<T>()
+                .unwrap_or_else(|_| panic!("Invalid rule. {} is not a number.", bound));
+            parse(start)..=parse(end)
+        }
+
+        value.split(|char: char| char.is_whitespace() || char == ',')
+            .filter(|setting| !setting.is_empty())
+            .fold(Self::default(), |mut rule, setting| {
+                let (key, value) = setting.split_once('=')
+                    .unwrap_or_else(|| panic!("Invalid rule. Missing '=' in {}.", setting));
+                match key {
+                    "steps" => rule.steps = parse_range(value),
+                    "direction" => rule.direction = value.into(),
+                    "plateau" => rule.max_plateau = value.parse()
+                        .unwrap_or_else(|_| panic!("Invalid rule. {} is not a number.", value)),
+                    "bounds" => rule.bounds = Some(parse_range(value)),
+                    _ => panic!("Invalid rule. Unknown setting {}.", key)
+                }
+                rule
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_from_string() {
+        assert_eq!(SafetyRule::from(""), SafetyRule::default());
+        let rule = SafetyRule::from("steps=2..=5, direction=decreasing plateau=2 bounds=-10..=10");
+        assert_eq!(rule, SafetyRule {
+            steps: 2..=5,
+            direction: Monotonicity::Decreasing,
+            max_plateau: 2,
+            bounds: Some(-10..=10)
+        });
+        assert_eq!(SafetyRule::from("direction=none").direction, Monotonicity::Unrestricted);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown setting")]
+    fn test_rule_from_string_unknown_setting() {
+        let _ = SafetyRule::from("slope=1..=3");
+    }
+
+    #[test]
+    fn test_is_safe_with_rules() {
+        let increasing = SafetyRule::from("direction=increasing");
+        assert!(increasing.is_safe(&[1, 2, 4]));
+        assert!(!increasing.is_safe(&[4, 2, 1]));
+
+        let unrestricted = SafetyRule::from("direction=none");
+        assert!(unrestricted.is_safe(&[1, 3, 2, 4]));
+        assert!(!unrestricted.is_safe(&[1, 5, 2]));
+
+        let plateau = SafetyRule::from("plateau=1");
+        assert!(plateau.is_safe(&[1, 1, 2, 2, 3]));
+        assert!(!plateau.is_safe(&[1, 1, 1, 2]));
+
+        let bounded = SafetyRule::from("bounds=0..=5");
+        assert!(bounded.is_safe(&[0, 2, 5]));
+        assert!(!bounded.is_safe(&[2, 5, 6]));
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let rule = SafetyRule::default();
+        assert_eq!(rule.diagnose(&[7, 6, 4, 2, 1]), None);
+        assert_eq!(rule.diagnose(&[1, 2, 7, 8, 9]), Some(Violation { index: 2, kind: ViolationKind::StepTooLarge }));
+        assert_eq!(rule.diagnose(&[1, 3, 2, 4, 5]), Some(Violation { index: 2, kind: ViolationKind::DirectionChange }));
+        assert_eq!(rule.diagnose(&[8, 6, 4, 4, 1]), Some(Violation { index: 3, kind: ViolationKind::ZeroStep }));
+        assert_eq!(SafetyRule::from("bounds=0..=5").diagnose(&[1, 2, 6]), Some(Violation { index: 2, kind: ViolationKind::OutOfBounds }));
+        assert_eq!(SafetyRule::from("steps=2..=3").diagnose(&[1, 3, 4]), Some(Violation { index: 2, kind: ViolationKind::StepTooSmall }));
+        assert_eq!(SafetyRule::from("direction=decreasing").diagnose(&[1, 2]), Some(Violation { index: 1, kind: ViolationKind::DirectionChange }));
+    }
+
+    #[test]
+    fn test_diagnose_agrees_with_is_safe() {
+        let rules = [
+            SafetyRule::default(),
+            SafetyRule::from("direction=none plateau=1"),
+            SafetyRule::from("direction=increasing bounds=0..=9")
+        ];
+        let reports = [
+            vec![1, 3, 2, 4, 5, 9, 6],
+            vec![5, 5, 5, 5],
+            vec![5, 5, 6, 7],
+            vec![1, 2, 3, 2, 1, 0, -1],
+            vec![3, 1, 4, 1, 5, 9, 2, 6]
+        ];
+        for rule in rules.iter() {
+            for report in reports.iter() {
+                assert_eq!(rule.diagnose(report).is_none(), rule.is_safe(report), "{:?} {:?}", rule, report);
+            }
+        }
+    }
+
+    #[test]
+    fn test_verdict() {
+        let rule = SafetyRule::default();
+        assert_eq!(rule.verdict(&[7, 6, 4, 2, 1], 1), Verdict::Safe);
+        assert_eq!(rule.verdict(&[9, 7, 6, 2, 1], 1), Verdict::Unsafe(Violation { index: 3, kind: ViolationKind::StepTooLarge }));
+        assert_eq!(rule.verdict(&[1, 3, 2, 4, 5], 1), Verdict::Dampened {
+            violation: Violation { index: 2, kind: ViolationKind::DirectionChange },
+            removals: vec![2]
+        });
+    }
+
+    #[test]
+    fn test_removals() {
+        let rule = SafetyRule::default();
+        assert_eq!(rule.removals(&[7, 6, 4, 2, 1], 1), Some(vec![]));
+        assert_eq!(rule.removals(&[1, 2, 7, 8, 9], 1), None);
+        assert_eq!(rule.removals(&[1, 3, 2, 4, 5], 1), Some(vec![2]));
+        assert_eq!(rule.removals(&[8, 6, 4, 4, 1], 1), Some(vec![3]));
+        assert_eq!(rule.removals(&[1, 3, 2, 4, 5], 0), None);
+        assert_eq!(rule.removals(&[1, 9, 2, 9, 3, 4], 2), Some(vec![1, 3]));
+        assert_eq!(rule.removals(&[], 0), Some(vec![]));
+        assert_eq!(SafetyRule::from("bounds=0..=9").removals(&[1, 2, 30, 3], 1), Some(vec![2]));
+        assert_eq!(SafetyRule::from("plateau=1").removals(&[1, 1, 1, 2], 1), Some(vec![2]));
+    }
+
+    #[test]
+    fn test_removals_match_brute_force() {
+        fn brute_force(rule: &SafetyRule, report: &[i32], tolerance: usize) -> Option<usize> {
+            (0..1usize << report.len())
+                .filter(|mask| mask.count_ones() as usize <= tolerance)
+                .filter(|mask| {
+                    let remaining: Vec<i32> = report.iter().enumerate()
+                        .filter(|(index, _)| mask & (1 << index) == 0)
+                        .map(|(_, level)| *level)
+                        .collect();
+                    rule.is_safe(&remaining)
+                })
+                .map(|mask| mask.count_ones() as usize)
+                .min()
+        }
+
+        let rules = [
+            SafetyRule::default(),
+            SafetyRule::from("direction=none"),
+            SafetyRule::from("plateau=1 steps=1..=2"),
+            SafetyRule::from("direction=decreasing bounds=-5..=8")
+        ];
+        let reports = [
+            vec![1, 3, 2, 4, 5, 9, 6],
+            vec![5, 5, 5, 5],
+            vec![10, 1, 8, 2, 6, 3, 4],
+            vec![1, 2, 3, 2, 1, 0, -1],
+            vec![1, 10, 2, 20, 3, 30, 4],
+            vec![4, 4, 3, 3, 3, 2, 9]
+        ];
+        for rule in rules.iter() {
+            for report in reports.iter() {
+                for tolerance in 0..=report.len() {
+                    let removals = rule.removals(report, tolerance);
+                    assert_eq!(removals.as_ref().map(|removals| removals.len()), brute_force(rule, report, tolerance), "{:?} {:?} with tolerance {}", rule, report, tolerance);
+                    if let Some(removals) = removals {
+                        let remaining: Vec<i32> = report.iter().enumerate()
+                            .filter(|(index, _)| !removals.contains(index))
+                            .map(|(_, level)| *level)
+                            .collect();
+                        assert!(rule.is_safe(&remaining), "{:?} {:?} with tolerance {}", rule, report, tolerance);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_longest_safe_run() {
+        let rule = SafetyRule::default();
+        assert_eq!(rule.longest_safe_run(&[7, 6, 4, 2, 1]), 0..5);
+        assert_eq!(rule.longest_safe_run(&[1, 2, 7, 8, 9]), 2..5);
+        assert_eq!(rule.longest_safe_run(&[1, 3, 2, 4, 5]), 2..5);
+        assert_eq!(rule.longest_safe_run(&[8, 6, 4, 4, 1]), 0..3);
+        assert_eq!(rule.longest_safe_run(&[]), 0..0);
+        assert_eq!(SafetyRule::from("plateau=1").longest_safe_run(&[1, 1, 1, 2, 3]), 1..5);
+        assert_eq!(SafetyRule::from("bounds=0..=9").longest_safe_run(&[20, 30]), 0..0);
+    }
+
+    #[test]
+    fn test_min_removals() {
+        let rule = SafetyRule::default();
+        assert_eq!(rule.min_removals(&[7, 6, 4, 2, 1]), 0);
+        assert_eq!(rule.min_removals(&[1, 2, 7, 8, 9]), 2);
+        assert_eq!(rule.min_removals(&[1, 3, 2, 4, 5]), 1);
+        assert_eq!(rule.min_removals(&[1, 9, 2, 9, 3, 9, 4]), 3);
+        assert_eq!(SafetyRule::from("bounds=0..=9").min_removals(&[20, 30]), 2);
+    }
+
+    #[test]
+    fn test_stability_matches_brute_force() {
+        fn brute_force_run(rule: &SafetyRule, report: &[i32]) -> usize {
+            (0..=report.len())
+                .flat_map(|start| (start..=report.len()).map(move |end| start..end))
+                .filter(|run| rule.is_safe(&report[run.clone()]))
+                .map(|run| run.len())
+                .max()
+                .unwrap_or(0)
+        }
+        fn brute_force_removals(rule: &SafetyRule, report: &[i32]) -> usize {
+            (0..1usize << report.len())
+                .filter(|mask| {
+                    let remaining: Vec<i32> = report.iter().enumerate()
+                        .filter(|(index, _)| mask & (1 << index) == 0)
+                        .map(|(_, level)| *level)
+                        .collect();
+                    rule.is_safe(&remaining)
+                })
+                .map(|mask| mask.count_ones() as usize)
+                .min()
+                .unwrap()
+        }
+
+        let rules = [
+            SafetyRule::default(),
+            SafetyRule::from("direction=none"),
+            SafetyRule::from("plateau=1 steps=1..=2"),
+            SafetyRule::from("plateau=2"),
+            SafetyRule::from("direction=decreasing bounds=-5..=8")
+        ];
+        let reports = [
+            vec![1, 3, 2, 4, 5, 9, 6],
+            vec![5, 5, 5, 5, 6],
+            vec![10, 1, 8, 2, 6, 3, 4],
+            vec![1, 2, 3, 2, 1, 0, -1],
+            vec![1, 10, 2, 20, 3, 30, 4],
+            vec![4, 4, 3, 3, 3, 2, 9],
+            vec![9, 9, 10, 10, 10, 10, 11, 12]
+        ];
+        for rule in rules.iter() {
+            for report in reports.iter() {
+                let run = rule.longest_safe_run(report);
+                assert!(rule.is_safe(&report[run.clone()]), "{:?} {:?}", rule, report);
+                assert_eq!(run.len(), brute_force_run(rule, report), "{:?} {:?}", rule, report);
+                assert_eq!(rule.min_removals(report), brute_force_removals(rule, report), "{:?} {:?}", rule, report);
+            }
+        }
+    }
+
+    #[test]
+    fn test_removals_long_report() {
+        let rule = SafetyRule::default();
+        let mut report: Vec<i32> = (0..10_000).collect();
+        report[5_000] = -50;
+        assert_eq!(rule.removals(&report, 1), Some(vec![5_000]));
+        assert_eq!(rule.removals(&report, 0), None);
+    }
+}
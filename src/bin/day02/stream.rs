@@ -0,0 +1,178 @@
+use std::io::{BufRead, Error, ErrorKind, Lines, Result, Write};
+use crate::safety_rule::SafetyRule;
+use crate::verdict::Verdict;
+
+/// Running totals over the reports seen so far
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub struct ReportCounts {
+    pub reports: usize,
+    pub safe: usize,
+    pub safe_dampened: usize
+}
+
+pub struct ValidatedReport {
+    pub line: String,
+    pub verdict: Verdict,
+    pub counts: ReportCounts
+}
+
+/**
+ * Validates reports one line at a time, so only the current report is ever held in memory.
+ * Blank lines are skipped, and a report that is safe within `tolerance` removals counts
+ * towards `safe_dampened`.
+ */
+pub struct ReportStream<'a, R: BufRead> {
+    lines: Lines<R>,
+    line_number: usize,
+    rule: &'a SafetyRule,
+    tolerance: usize,
+    counts: ReportCounts
+}
+
+impl<'a, R: BufRead> ReportStream<'a, R> {
+    pub fn new(input: R, rule: &'a SafetyRule, tolerance: usize) -> Self {
+        Self {
+            lines: input.lines(),
+            line_number: 0,
+            rule,
+            tolerance,
+            counts: ReportCounts::default()
+        }
+    }
+
+    fn parse_line(&self, line: &str) -> Result<Vec<i32>> {
+        line.split_whitespace()
+            .map(|item| item.parse::<i32>().map_err(|_| Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid input on line {}. {} is not a number.", self.line_number, item)
+            )))
+            .collect()
+    }
+}
+
+impl<R: BufRead> Iterator for ReportStream<'_, R> {
+    type Item = Result<ValidatedReport>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(error) => return Some(Err(error))
+            };
+            self.line_number += 1;
+            let report = match self.parse_line(&line) {
+                Ok(report) => report,
+                Err(error) => return Some(Err(error))
+            };
+            if report.is_empty() {
+                continue;
+            }
+            let verdict = self.rule.verdict(&report, self.tolerance);
+            self.counts.reports += 1;
+            match verdict {
+                Verdict::Safe => {
+                    self.counts.safe += 1;
+                    self.counts.safe_dampened += 1;
+                }
+                Verdict::Dampened { .. } => self.counts.safe_dampened += 1,
+                Verdict::Unsafe(_) => {}
+            }
+            return Some(Ok(ValidatedReport { line, verdict, counts: self.counts }));
+        }
+    }
+}
+
+/// Where `filter_reports` copies each kind of report, leaving out any kind without a writer
+#[derive(Default)]
+pub struct ReportOutputs<'a> {
+    /// Reports that are safe as they are, as counted by Part 1
+    pub safe: Option<&'a mut dyn Write>,
+    /// Reports that are only safe once some levels are removed, which Part 2 counts as well
+    pub dampened: Option<&'a mut dyn Write>,
+    pub unsafe_reports: Option<&'a mut dyn Write>
+}
+
+/**
+ * Streams reports from `input`, copying each line to the output for its verdict and calling
+ * `progress` with the running counts after every report. Returns the final counts.
+ */
+pub fn filter_reports<R: BufRead>(
+    input: R,
+    rule: &SafetyRule,
+    tolerance: usize,
+    mut outputs: ReportOutputs,
+    mut progress: impl FnMut(&ReportCounts)
+) -> Result<ReportCounts> {
+    let mut counts = ReportCounts::default();
+    for validated in ReportStream::new(input, rule, tolerance) {
+        let validated = validated?;
+        let output = match validated.verdict {
+            Verdict::Safe => &mut outputs.safe,
+            Verdict::Dampened { .. } => &mut outputs.dampened,
+            Verdict::Unsafe(_) => &mut outputs.unsafe_reports
+        };
+        if let Some(output) = output {
+            writeln!(output, "{}", validated.line)?;
+        }
+        counts = validated.counts;
+        progress(&counts);
+    }
+    for output in [outputs.safe, outputs.dampened, outputs.unsafe_reports].into_iter().flatten() {
+        output.flush()?;
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "7 6 4 2 1\n1 2 7 8 9\n9 7 6 2 1\n1 3 2 4 5\n8 6 4 4 1\n1 3 6 7 9\n";
+
+    #[test]
+    fn test_report_stream_counts() {
+        let rule = SafetyRule::default();
+        let counts: Vec<ReportCounts> = ReportStream::new(SAMPLE.as_bytes(), &rule, 1)
+            .map(|validated| validated.unwrap().counts)
+            .collect();
+        assert_eq!(counts.len(), 6);
+        assert_eq!(counts[1], ReportCounts { reports: 2, safe: 1, safe_dampened: 1 });
+        assert_eq!(counts[5], ReportCounts { reports: 6, safe: 2, safe_dampened: 4 });
+    }
+
+    #[test]
+    fn test_report_stream_invalid_line() {
+        let rule = SafetyRule::default();
+        let mut stream = ReportStream::new("1 2 3\n\n4 x 6\n".as_bytes(), &rule, 1);
+        assert!(stream.next().unwrap().is_ok());
+        let error = stream.next().unwrap().err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "Invalid input on line 3. x is not a number.");
+    }
+
+    #[test]
+    fn test_filter_reports() {
+        let rule = SafetyRule::default();
+        let mut safe = Vec::new();
+        let mut unsafe_reports = Vec::new();
+        let mut updates = 0;
+        let outputs = ReportOutputs { safe: Some(&mut safe), dampened: None, unsafe_reports: Some(&mut unsafe_reports) };
+        let counts = filter_reports(SAMPLE.as_bytes(), &rule, 0, outputs, |_| updates += 1).unwrap();
+        assert_eq!(counts, ReportCounts { reports: 6, safe: 2, safe_dampened: 2 });
+        assert_eq!(updates, 6);
+        assert_eq!(String::from_utf8(safe).unwrap(), "7 6 4 2 1\n1 3 6 7 9\n");
+        assert_eq!(String::from_utf8(unsafe_reports).unwrap(), "1 2 7 8 9\n9 7 6 2 1\n1 3 2 4 5\n8 6 4 4 1\n");
+    }
+
+    #[test]
+    fn test_filter_reports_dampened() {
+        let rule = SafetyRule::default();
+        let (mut safe, mut dampened, mut unsafe_reports) = (Vec::new(), Vec::new(), Vec::new());
+        let outputs = ReportOutputs { safe: Some(&mut safe), dampened: Some(&mut dampened), unsafe_reports: Some(&mut unsafe_reports) };
+        let counts = filter_reports(SAMPLE.as_bytes(), &rule, 1, outputs, |_| {}).unwrap();
+        assert_eq!(counts, ReportCounts { reports: 6, safe: 2, safe_dampened: 4 });
+        assert_eq!(String::from_utf8(safe).unwrap(), "7 6 4 2 1\n1 3 6 7 9\n");
+        assert_eq!(String::from_utf8(dampened).unwrap(), "1 3 2 4 5\n8 6 4 4 1\n");
+        assert_eq!(String::from_utf8(unsafe_reports).unwrap(), "1 2 7 8 9\n9 7 6 2 1\n");
+    }
+}
@@ -0,0 +1,59 @@
+use std::fmt::{Display, Formatter, Result};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ViolationKind {
+    DirectionChange,
+    StepTooLarge,
+    StepTooSmall,
+    ZeroStep,
+    OutOfBounds
+}
+
+impl Display for ViolationKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let name = match self {
+            ViolationKind::DirectionChange => "direction change",
+            ViolationKind::StepTooLarge => "step too large",
+            ViolationKind::StepTooSmall => "step too small",
+            ViolationKind::ZeroStep => "zero step",
+            ViolationKind::OutOfBounds => "out of bounds"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/**
+ * The first place a report breaks its safety rule. `index` is the level the violation was
+ * found at, so for steps it is the level the bad step leads to.
+ */
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Violation {
+    pub index: usize,
+    pub kind: ViolationKind
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{} at index {}", self.kind, self.index)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Verdict {
+    Safe,
+    /// Unsafe as reported, but removing the levels at these indices makes it safe
+    Dampened { violation: Violation, removals: Vec<usize> },
+    Unsafe(Violation)
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Verdict::Safe => write!(f, "safe"),
+            Verdict::Dampened { violation, removals } => {
+                write!(f, "safe after removing {:?} ({})", removals, violation)
+            }
+            Verdict::Unsafe(violation) => write!(f, "unsafe ({})", violation)
+        }
+    }
+}
@@ -0,0 +1,62 @@
+#[derive(Clone, Copy)]
+pub enum Effect {
+    /// Produces a value from its operands, which is added to the total at the top level. `None`
+    /// means the value overflowed
+    Value(fn(&[i64]) -> Option<i64>),
+    Enable,
+    Disable
+}
+
+#[derive(Clone, Copy)]
+pub struct Instruction {
+    pub name: &'static str,
+    pub arity: usize,
+    pub effect: Effect
+}
+
+impl Instruction {
+    pub fn value(name: &'static str, arity: usize, apply: fn(&[i64]) -> Option<i64>) -> Self {
+        Self { name, arity, effect: Effect::Value(apply) }
+    }
+}
+
+/**
+ * The instructions the lexer recognizes and the interpreter executes. When `allow_nesting` is
+ * set, an operand may itself be a value instruction, e.g. `mul(mul(1,2),3)`.
+ */
+#[derive(Clone)]
+pub struct InstructionSet {
+    instructions: Vec<Instruction>,
+    pub allow_nesting: bool
+}
+
+impl InstructionSet {
+    pub fn new() -> Self {
+        Self { instructions: Vec::new(), allow_nesting: false }
+    }
+
+    /// Adds an instruction, replacing any existing one with the same name
+    pub fn register(&mut self, instruction: Instruction) {
+        self.instructions.retain(|existing| existing.name != instruction.name);
+        self.instructions.push(instruction);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Instruction> {
+        self.instructions.iter().find(|instruction| instruction.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Instruction> {
+        self.instructions.iter()
+    }
+}
+
+/// `mul(a,b)`, `do()` and `don't()` from the puzzle
+impl Default for InstructionSet {
+    fn default() -> Self {
+        let mut set = Self::new();
+        set.register(Instruction::value("mul", 2, |operands| operands[0].checked_mul(operands[1])));
+        set.register(Instruction { name: "do", arity: 0, effect: Effect::Enable });
+        set.register(Instruction { name: "don't", arity: 0, effect: Effect::Disable });
+        set
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt::{Display, Formatter};
+use crate::instruction::{Effect, InstructionSet};
+use crate::lexer::{Operand, Spanned, Token};
+use crate::trace::TraceStep;
+
+/// An instruction, or adding its value to the total, didn't fit in the interpreter's integers
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Overflow {
+    pub token: Token
+}
+
+impl Display for Overflow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Overflow while executing {}", self.token)
+    }
+}
+
+/**
+ * Executes tokens in order. When `conditional` is set, a disabling instruction like `don't()`
+ * turns off every value instruction until the next enabling one, otherwise those are ignored.
+ *
+ * Instructions produce 64-bit values and the total is kept in 128 bits, both checked so an
+ * overflow is reported rather than wrapping. With a `modulus` the total is instead kept reduced
+ * modulo it, so only the instructions themselves can overflow.
+ */
+pub struct Interpreter<'a> {
+    instructions: &'a InstructionSet,
+    conditional: bool,
+    modulus: Option<i128>,
+    enabled: bool,
+    total: i128
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(instructions: &'a InstructionSet, conditional: bool, modulus: Option<i128>) -> Self {
+        if let Some(modulus) = modulus {
+            assert!(modulus > 0, "Invalid modulus. {} is not positive.", modulus);
+        }
+        Self { instructions, conditional, modulus, enabled: true, total: 0 }
+    }
+
+    /// Executes a token, returning the value it added to the total if it added one
+    pub fn execute(&mut self, token: &Token) -> Result<Option<i64>, Overflow> {
+        match self.effect(token) {
+            Effect::Value(_) => {
+                if !self.enabled {
+                    return Ok(None);
+                }
+                let overflow = || Overflow { token: token.clone() };
+                let value = self.evaluate(token).ok_or_else(overflow)?;
+                self.total = match self.modulus {
+                    Some(modulus) => {
+                        // Both are below the modulus, so this wraps round without ever going past it
+                        let value = (value as i128).rem_euclid(modulus);
+                        if self.total >= modulus - value { self.total - (modulus - value) } else { self.total + value }
+                    }
+                    None => self.total.checked_add(value as i128).ok_or_else(overflow)?
+                };
+                return Ok(Some(value));
+            }
+            Effect::Enable => self.enabled = true,
+            Effect::Disable => self.enabled = !self.conditional
+        }
+        Ok(None)
+    }
+
+    pub fn total(&self) -> i128 {
+        self.total
+    }
+
+    pub fn run(mut self, tokens: impl IntoIterator<Item = Token>) -> Result<i128, Overflow> {
+        for token in tokens {
+            self.execute(&token)?;
+        }
+        Ok(self.total)
+    }
+
+    /**
+     * Executes tokens one at a time, recording the interpreter's state around each of them.
+     * Tracing stops after the first overflow.
+     */
+    pub fn trace<I>(mut self, tokens: I) -> impl Iterator<Item = Result<TraceStep, Overflow>> + 'a
+    where
+        I: IntoIterator<Item = Spanned>,
+        I::IntoIter: 'a
+    {
+        tokens.into_iter().scan(false, move |overflowed, spanned| {
+            if *overflowed {
+                return None;
+            }
+            let enabled = self.enabled;
+            let is_value = matches!(self.effect(&spanned.token), Effect::Value(_));
+            let step = self.execute(&spanned.token).map(|value| TraceStep {
+                span: spanned.span,
+                token: spanned.token,
+                enabled,
+                ignored: is_value && !enabled,
+                value,
+                total: self.total,
+                enabled_after: self.enabled
+            });
+            *overflowed = step.is_err();
+            Some(step)
+        })
+    }
+
+    fn effect(&self, token: &Token) -> Effect {
+        self.instructions.get(token.name)
+            .unwrap_or_else(|| panic!("Unknown instruction {}", token.name))
+            .effect
+    }
+
+    /// The value of a value instruction, or `None` if it or one of its operands overflows
+    fn evaluate(&self, token: &Token) -> Option<i64> {
+        let operands = token.operands.iter()
+            .map(|operand| match operand {
+                Operand::Number(value) => Some(*value),
+                Operand::Nested(nested) => self.evaluate(nested)
+            })
+            .collect::<Option<Vec<i64>>>()?;
+        match self.effect(token) {
+            Effect::Value(apply) => apply(&operands),
+            _ => panic!("{} does not produce a value", token.name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::Instruction;
+    use crate::lexer::Lexer;
+
+    fn run(source: &str, instructions: &InstructionSet, modulus: Option<i128>) -> Result<i128, Overflow> {
+        Interpreter::new(instructions, false, modulus)
+            .run(Lexer::new(source, instructions).map(|spanned| spanned.token))
+    }
+
+    fn nested_set() -> InstructionSet {
+        let mut instructions = InstructionSet::default();
+        instructions.register(Instruction::value("add", 2, |operands| operands[0].checked_add(operands[1])));
+        instructions.register(Instruction::value("neg", 1, |operands| operands[0].checked_neg()));
+        instructions.allow_nesting = true;
+        instructions
+    }
+
+    #[test]
+    fn test_total_wider_than_i32() {
+        let instructions = InstructionSet::default();
+        let source = "mul(999,999)".repeat(3_000);
+        assert_eq!(run(&source, &instructions, None), Ok(998_001 * 3_000));
+    }
+
+    #[test]
+    fn test_instruction_overflow() {
+        let instructions = nested_set();
+        // 999^6 fits in an i64 but 999^6 * 999^2 does not
+        let fits = "mul(mul(mul(mul(mul(999,999),999),999),999),999)";
+        assert_eq!(run(fits, &instructions, None), Ok(999i128.pow(6)));
+        let overflows = format!("mul(1,2)mul({},mul(999,999))", fits);
+        let error = run(&overflows, &instructions, None).unwrap_err();
+        assert_eq!(error.token.to_string(), format!("mul({},mul(999,999))", fits));
+    }
+
+    #[test]
+    fn test_modulus() {
+        let instructions = nested_set();
+        assert_eq!(run("mul(999,999)mul(2,3)", &instructions, Some(1_000)), Ok((998_001 + 6) % 1_000));
+        assert_eq!(run("neg(7)mul(1,2)", &instructions, Some(10)), Ok(5));
+        let overflows = "mul(mul(mul(mul(mul(mul(999,999),999),999),999),999),999)";
+        assert!(run(overflows, &instructions, Some(10)).is_err());
+        // Both values reduce to just under the modulus, so their sum wouldn't fit before reducing
+        assert_eq!(run("neg(1)neg(2)", &instructions, Some(i128::MAX)), Ok(i128::MAX - 3));
+    }
+
+    #[test]
+    fn test_trace_stops_at_overflow() {
+        let instructions = nested_set();
+        let source = "mul(2,2)mul(mul(mul(mul(mul(mul(999,999),999),999),999),999),999)mul(3,3)";
+        let steps: Vec<_> = Interpreter::new(&instructions, false, None)
+            .trace(Lexer::new(source, &instructions))
+            .collect();
+        assert_eq!(steps.len(), 2);
+        assert!(steps[0].is_ok());
+        assert!(steps[1].is_err());
+    }
+}
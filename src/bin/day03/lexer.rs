@@ -0,0 +1,256 @@
+use std::cell::Cell;
+use std::fmt::{Display, Formatter, Result};
+use std::ops::Range;
+use crate::instruction::{Effect, InstructionSet};
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Operand {
+    Number(i64),
+    Nested(Token)
+}
+
+/// A call to one of the instructions in the set, like `mul(2,4)`
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Token {
+    pub name: &'static str,
+    pub operands: Vec<Operand>
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Operand::Number(value) => write!(f, "{}", value),
+            Operand::Nested(token) => write!(f, "{}", token)
+        }
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let operands: Vec<String> = self.operands.iter().map(|operand| operand.to_string()).collect();
+        write!(f, "{}({})", self.name, operands.join(","))
+    }
+}
+
+/// A token along with the bytes of the source it was read from
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Spanned {
+    pub token: Token,
+    pub span: Range<usize>
+}
+
+/// How many instructions deep operands can be nested. Anything deeper is treated as corrupted,
+/// which also bounds how far the lexer looks ahead from any one byte
+pub const MAX_NESTING: usize = 32;
+
+/**
+ * Scans corrupted memory for instructions. Anything that isn't exactly an instruction is
+ * skipped one byte at a time, so an instruction hidden inside a corrupted one is still found.
+ *
+ * A lexer over only part of the memory stops at the first place an instruction could continue
+ * past the end of what it was given, leaving `position` there so the rest can be lexed once
+ * more memory has been read.
+ */
+pub struct Lexer<'a> {
+    source: &'a [u8],
+    instructions: &'a InstructionSet,
+    position: usize,
+    complete: bool,
+    truncated: Cell<bool>
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str, instructions: &'a InstructionSet) -> Self {
+        Self::from_bytes(source.as_bytes(), instructions, true)
+    }
+
+    /// Lexes raw memory. `complete` is whether it is all of the memory or only the start of it
+    pub fn from_bytes(source: &'a [u8], instructions: &'a InstructionSet, complete: bool) -> Self {
+        Self { source, instructions, position: 0, complete, truncated: Cell::new(false) }
+    }
+
+    /// How far into the source the lexer has read
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /**
+     * Tries to read a token at `start`, `depth` operands deep, returning it and the position
+     * just after it. Only value instructions are tried as operands, since they are the only ones
+     * with a value to use.
+     */
+    fn token_at(&self, start: usize, depth: usize) -> Option<(Token, usize)> {
+        self.instructions.iter()
+            .filter(|instruction| depth == 0 || matches!(instruction.effect, Effect::Value(_)))
+            .find_map(|instruction| {
+                let mut position = self.expect(start, instruction.name.as_bytes())?;
+                position = self.expect(position, b"(")?;
+                let mut operands = Vec::with_capacity(instruction.arity);
+                for index in 0..instruction.arity {
+                    if index > 0 {
+                        position = self.expect(position, b",")?;
+                    }
+                    let (operand, next) = self.operand(position, depth)?;
+                    operands.push(operand);
+                    position = next;
+                }
+                position = self.expect(position, b")")?;
+                Some((Token { name: instruction.name, operands }, position))
+            })
+    }
+
+    fn expect(&self, position: usize, expected: &[u8]) -> Option<usize> {
+        let rest = &self.source[position..];
+        if rest.len() < expected.len() && expected.starts_with(rest) {
+            self.truncated.set(true);
+        }
+        rest.starts_with(expected).then_some(position + expected.len())
+    }
+
+    /// Reads an operand of a token that is `depth` operands deep
+    fn operand(&self, start: usize, depth: usize) -> Option<(Operand, usize)> {
+        if let Some((value, position)) = self.number(start) {
+            return Some((Operand::Number(value), position));
+        }
+        if !self.instructions.allow_nesting || depth == MAX_NESTING {
+            return None;
+        }
+        self.token_at(start, depth + 1).map(|(token, position)| (Operand::Nested(token), position))
+    }
+
+    /// Reads a number of one to three digits
+    fn number(&self, start: usize) -> Option<(i64, usize)> {
+        let digits = self.source[start..].iter()
+            .take(4)
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+        if start + digits == self.source.len() {
+            // More digits could follow in the rest of the memory
+            self.truncated.set(true);
+        }
+        if !(1..=3).contains(&digits) {
+            return None;
+        }
+        let value = self.source[start..start + digits].iter()
+            .fold(0, |value, digit| value * 10 + (digit - b'0') as i64);
+        Some((value, start + digits))
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Spanned;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position < self.source.len() {
+            let start = self.position;
+            self.truncated.set(false);
+            let token = self.token_at(start, 0);
+            if self.truncated.get() && !self.complete {
+                return None;
+            }
+            match token {
+                Some((token, end)) => {
+                    self.position = end;
+                    return Some(Spanned { token, span: start..end });
+                }
+                None => self.position += 1
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::Instruction;
+
+    fn call(name: &'static str, operands: &[i64]) -> Token {
+        Token { name, operands: operands.iter().map(|operand| Operand::Number(*operand)).collect() }
+    }
+
+    fn tokens(source: &str, instructions: &InstructionSet) -> Vec<Token> {
+        Lexer::new(source, instructions).map(|spanned| spanned.token).collect()
+    }
+
+    #[test]
+    fn test_lexer_spans() {
+        let instructions = InstructionSet::default();
+        let spanned: Vec<Spanned> = Lexer::new("xmul(2,4)do()_don't()", &instructions).collect();
+        assert_eq!(spanned, vec![
+            Spanned { token: call("mul", &[2, 4]), span: 1..9 },
+            Spanned { token: call("do", &[]), span: 9..13 },
+            Spanned { token: call("don't", &[]), span: 14..21 }
+        ]);
+    }
+
+    #[test]
+    fn test_lexer_operand_digits() {
+        let instructions = InstructionSet::default();
+        assert_eq!(tokens("mul(,4)mul(2,)mul(,)", &instructions), vec![]);
+        assert_eq!(tokens("mul(1234,5)mul(123,456)", &instructions), vec![call("mul", &[123, 456])]);
+        assert_eq!(tokens("mul(1,2345)mul( 1,2)mul(1,2 )", &instructions), vec![]);
+    }
+
+    #[test]
+    fn test_lexer_recovers_inside_corrupted_instruction() {
+        let instructions = InstructionSet::default();
+        assert_eq!(tokens("mul(mul(2,3)", &instructions), vec![call("mul", &[2, 3])]);
+        assert_eq!(tokens("mul(32,64]then(mul(11,8)", &instructions), vec![call("mul", &[11, 8])]);
+        assert_eq!(tokens("don't(do()", &instructions), vec![call("do", &[])]);
+    }
+
+    #[test]
+    fn test_lexer_non_ascii() {
+        let instructions = InstructionSet::default();
+        assert_eq!(tokens("é mul(1,2) ü", &instructions), vec![call("mul", &[1, 2])]);
+    }
+
+    #[test]
+    fn test_partial_lexer_stops_at_truncated_instruction() {
+        let instructions = InstructionSet::default();
+        let mut lexer = Lexer::from_bytes(b"mul(1,2)xmul(3,4", &instructions, false);
+        assert_eq!(lexer.next(), Some(Spanned { token: call("mul", &[1, 2]), span: 0..8 }));
+        assert_eq!(lexer.next(), None);
+        assert_eq!(lexer.position(), 9);
+
+        let mut lexer = Lexer::from_bytes(b"mul(1,2)don", &instructions, false);
+        assert_eq!(lexer.next().map(|spanned| spanned.token), Some(call("mul", &[1, 2])));
+        assert_eq!(lexer.next(), None);
+        assert_eq!(lexer.position(), 8);
+
+        let mut lexer = Lexer::from_bytes(b"mul(1,2]xyz", &instructions, false);
+        assert_eq!(lexer.next(), None);
+        assert_eq!(lexer.position(), 11);
+    }
+
+    #[test]
+    fn test_lexer_nesting() {
+        let mut instructions = InstructionSet::default();
+        instructions.register(Instruction::value("neg", 1, |operands| operands[0].checked_neg()));
+        assert_eq!(tokens("mul(mul(1,2),3)neg(4)", &instructions), vec![call("mul", &[1, 2]), call("neg", &[4])]);
+        instructions.allow_nesting = true;
+        assert_eq!(tokens("mul(mul(1,2),3)do(do())mul(neg(do()),1)", &instructions), vec![
+            Token {
+                name: "mul",
+                operands: vec![Operand::Nested(call("mul", &[1, 2])), Operand::Number(3)]
+            },
+            call("do", &[]),
+            call("do", &[])
+        ]);
+    }
+
+    #[test]
+    fn test_lexer_nesting_depth() {
+        let mut instructions = InstructionSet::default();
+        instructions.allow_nesting = true;
+        let nested = |count: usize| format!("{}1,1){}", "mul(".repeat(count), ",1)".repeat(count - 1));
+        let deepest = nested(MAX_NESTING + 1);
+        assert_eq!(tokens(&deepest, &instructions).len(), 1);
+        // One level too deep, so only the instruction inside the outermost one is read
+        let found = tokens(&nested(MAX_NESTING + 2), &instructions);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].to_string(), deepest);
+        assert_eq!(tokens(&"mul(".repeat(20_000), &instructions), vec![]);
+    }
+}
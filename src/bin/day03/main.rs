@@ -1,43 +1,92 @@
-use regex::Regex;
+use std::io::stdin;
+use instruction::InstructionSet;
+use interpreter::{Interpreter, Overflow};
+use lexer::Lexer;
+use stream::StreamLexer;
+
+mod instruction;
+mod interpreter;
+mod lexer;
+mod stream;
+mod trace;
+
+/// How many bytes of memory to read at a time when streaming
+const CHUNK_SIZE: usize = 64 * 1024;
 
 fn main() {
+    // `--stream` runs both parts over memory from stdin, a chunk at a time. `--trace` lists every
+    // instruction executed by part 2, and `--annotate` prints the input with those instructions
+    // highlighted and disabled regions dimmed. `--modulo=<n>` reports the totals modulo n
+    let flags: Vec<String> = std::env::args().skip(1).collect();
+    let modulus = flags.iter()
+        .find_map(|flag| flag.strip_prefix("--modulo="))
+        .map(|modulus| modulus.parse::<i128>().unwrap_or_else(|_| panic!("Invalid input. {} is not a number.", modulus)));
+    if flags.iter().any(|flag| flag == "--stream") {
+        stream_stdin(modulus);
+        return;
+    }
     let instructions = parse_input("./src/bin/day03/input.txt");
+    if flags.iter().any(|flag| flag == "--trace" || flag == "--annotate") {
+        let set = InstructionSet::default();
+        let steps: Vec<_> = Interpreter::new(&set, true, modulus)
+            .trace(Lexer::new(&instructions, &set))
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|overflow| panic!("{}", overflow));
+        if flags.iter().any(|flag| flag == "--trace") {
+            print!("{}", trace::trace_table(&steps));
+        }
+        if flags.iter().any(|flag| flag == "--annotate") {
+            println!("{}", trace::annotate(&instructions, &steps));
+        }
+    }
     // Part 1
-    let value = interpret(&instructions);
+    let value = interpret(&instructions, modulus).unwrap_or_else(|overflow| panic!("{}", overflow));
     println!("{}", value);
     // Part 2
-    let new_value = conditional_interpret(&instructions);
+    let new_value = conditional_interpret(&instructions, modulus).unwrap_or_else(|overflow| panic!("{}", overflow));
     println!("{}", new_value);
 }
 
+/**
+ * Runs both parts in a single pass over stdin, with one interpreter per part sharing the
+ * same tokens.
+ */
+fn stream_stdin(modulus: Option<i128>) {
+    let set = InstructionSet::default();
+    let mut interpreter = Interpreter::new(&set, false, modulus);
+    let mut conditional_interpreter = Interpreter::new(&set, true, modulus);
+    for spanned in StreamLexer::new(stdin().lock(), &set, CHUNK_SIZE) {
+        let token = spanned.unwrap_or_else(|error| panic!("Error reading memory: {}", error)).token;
+        interpreter.execute(&token).unwrap_or_else(|overflow| panic!("{}", overflow));
+        conditional_interpreter.execute(&token).unwrap_or_else(|overflow| panic!("{}", overflow));
+    }
+    // Part 1
+    println!("{}", interpreter.total());
+    // Part 2
+    println!("{}", conditional_interpreter.total());
+}
+
 fn parse_input(file_path: &str) -> String {
     std::fs::read_to_string(file_path).expect(&format!("Error reading file path: {}", file_path))
 }
 
-fn interpret(instructions: &str) -> i32 {
-    Regex::new(r"mul\((\d*?),(\d*?)\)")
-        .unwrap()
-        .captures_iter(instructions)
-        .map(|captures| {
-            captures
-                .extract::<2>()
-                .1
-                .iter()
-                .fold(1, |accumulator, capture| accumulator * capture.parse::<i32>().unwrap())
-        }).sum()
+fn interpret(instructions: &str, modulus: Option<i128>) -> Result<i128, Overflow> {
+    execute(instructions, &InstructionSet::default(), false, modulus)
+}
+
+fn conditional_interpret(instructions: &str, modulus: Option<i128>) -> Result<i128, Overflow> {
+    execute(instructions, &InstructionSet::default(), true, modulus)
 }
 
-fn conditional_interpret(instructions: &str) -> i32 {
-    // (?s) flag is required here to set dotall mode so that . includes newlines
-    let new_instructions = Regex::new(r"(?s)(don't\(\).*?(?:do\(\)|$))")
-        .unwrap()
-        .replace_all(instructions, "");
-    interpret(&new_instructions)
+fn execute(source: &str, instructions: &InstructionSet, conditional: bool, modulus: Option<i128>) -> Result<i128, Overflow> {
+    Interpreter::new(instructions, conditional, modulus)
+        .run(Lexer::new(source, instructions).map(|spanned| spanned.token))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use instruction::{Effect, Instruction};
 
     #[test]
     fn test_parse_input() {
@@ -48,30 +97,75 @@ mod tests {
     #[test]
     fn test_interpret() {
         let input = "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
-        assert_eq!(interpret(input), 161);
+        assert_eq!(interpret(input, None), Ok(161));
     }
 
     #[test]
     fn test_conditional_interpret() {
         let input = "xdo()mul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))don't()mul(1,2)";
-        assert_eq!(conditional_interpret(input), 48);
+        assert_eq!(conditional_interpret(input, None), Ok(48));
     }
 
     #[test]
     fn test_conditional_interpret_with_dangling_dont() {
         let input = "mul(1,2)mul(2,2)don't()mul(100,100)";
-        assert_eq!(conditional_interpret(input), 6);
+        assert_eq!(conditional_interpret(input, None), Ok(6));
     }
 
     #[test]
     fn test_conditional_interpret_consecutive_donts() {
         let input = "mul(1,2)don't()don't()don't()mul(1,5)don't()mul(1,100)do()";
-        assert_eq!(conditional_interpret(input), 2);
+        assert_eq!(conditional_interpret(input, None), Ok(2));
     }
 
     #[test]
     fn test_conditional_interpret_with_newlines() {
         let input = "mul(1,2)don't()\nmul(1,5)don't()mul(1,100)do()";
-        assert_eq!(conditional_interpret(input), 2);
+        assert_eq!(conditional_interpret(input, None), Ok(2));
+    }
+
+    #[test]
+    fn test_interpret_ignores_empty_and_long_operands() {
+        let input = "mul(,2)mul(2,)mul(1234,1)mul(3,4)";
+        assert_eq!(interpret(input, None), Ok(12));
+    }
+
+    #[test]
+    fn test_execute_with_extra_instructions() {
+        let mut instructions = InstructionSet::default();
+        instructions.register(Instruction::value("add", 2, |operands| operands[0].checked_add(operands[1])));
+        instructions.register(Instruction::value("sub", 2, |operands| operands[0].checked_sub(operands[1])));
+        instructions.register(Instruction::value("neg", 1, |operands| operands[0].checked_neg()));
+        let input = "add(1,2)sub(10,4)don't()neg(5)do()neg(7)";
+        assert_eq!(execute(input, &instructions, false, None), Ok(3 + 6 - 5 - 7));
+        assert_eq!(execute(input, &instructions, true, None), Ok(3 + 6 - 7));
+
+        instructions.allow_nesting = true;
+        assert_eq!(execute("mul(mul(1,2),3)x add(neg(4),sub(9,mul(2,2)))", &instructions, false, None), Ok(6 + 1));
+    }
+
+    #[test]
+    fn test_execute_with_renamed_conditionals() {
+        let mut instructions = InstructionSet::new();
+        instructions.register(Instruction::value("mul", 2, |operands| operands[0].checked_mul(operands[1])));
+        instructions.register(Instruction { name: "on", arity: 0, effect: Effect::Enable });
+        instructions.register(Instruction { name: "off", arity: 0, effect: Effect::Disable });
+        assert_eq!(execute("mul(2,3)off()mul(4,5)do()mul(1,1)on()mul(1,2)", &instructions, true, None), Ok(8));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_interpret_stream() {
+        let input = "xdo()mul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))don't()mul(1,2)";
+        let instructions = InstructionSet::default();
+        let tokens = || StreamLexer::new(input.as_bytes(), &instructions, 5).map(|spanned| spanned.unwrap().token);
+        assert_eq!(Interpreter::new(&instructions, false, None).run(tokens()), interpret(input, None));
+        assert_eq!(Interpreter::new(&instructions, true, None).run(tokens()), Ok(48));
+    }
+
+    #[test]
+    fn test_interpret_modulo() {
+        let input = "xdo()mul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))don't()mul(1,2)";
+        assert_eq!(interpret(input, Some(100)), Ok(163 % 100));
+        assert_eq!(conditional_interpret(input, Some(7)), Ok(48 % 7));
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::{ErrorKind, Read, Result};
+use crate::instruction::InstructionSet;
+use crate::lexer::{Lexer, Spanned};
+
+/**
+ * Lexes memory from a reader a chunk at a time. Only the unlexed tail of the previous chunk is
+ * carried over, so memory use stays around `chunk_size` plus the longest instruction no matter
+ * how large the input is. Spans are offsets into the whole input.
+ */
+pub struct StreamLexer<'a, R: Read> {
+    reader: R,
+    instructions: &'a InstructionSet,
+    chunk_size: usize,
+    buffer: Vec<u8>,
+    // Offset of `buffer` in the whole input, and how much of it has been lexed
+    offset: usize,
+    position: usize,
+    end_of_input: bool
+}
+
+impl<'a, R: Read> StreamLexer<'a, R> {
+    pub fn new(reader: R, instructions: &'a InstructionSet, chunk_size: usize) -> Self {
+        Self {
+            reader,
+            instructions,
+            chunk_size: chunk_size.max(1),
+            buffer: Vec::new(),
+            offset: 0,
+            position: 0,
+            end_of_input: false
+        }
+    }
+
+    /// Drops the lexed part of the buffer and appends the next chunk from the reader
+    fn read_chunk(&mut self) -> Result<()> {
+        self.buffer.drain(..self.position);
+        self.offset += self.position;
+        self.position = 0;
+        let length = self.buffer.len();
+        self.buffer.resize(length + self.chunk_size, 0);
+        let read = loop {
+            match self.reader.read(&mut self.buffer[length..]) {
+                Ok(read) => break read,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => {
+                    self.buffer.truncate(length);
+                    return Err(error);
+                }
+            }
+        };
+        self.buffer.truncate(length + read);
+        self.end_of_input = read == 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for StreamLexer<'_, R> {
+    type Item = Result<Spanned>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut lexer = Lexer::from_bytes(&self.buffer[self.position..], self.instructions, self.end_of_input);
+            let token = lexer.next();
+            let start = self.position;
+            self.position += lexer.position();
+            if let Some(spanned) = token {
+                let base = self.offset + start;
+                return Some(Ok(Spanned { token: spanned.token, span: base + spanned.span.start..base + spanned.span.end }));
+            }
+            if self.end_of_input {
+                return None;
+            }
+            if let Err(error) = self.read_chunk() {
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::Instruction;
+    use crate::lexer::MAX_NESTING;
+
+    const SAMPLE: &str = "xdo()mul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))don't()mul(1,2)";
+
+    #[test]
+    fn test_stream_matches_whole_input() {
+        let mut instructions = InstructionSet::default();
+        instructions.register(Instruction::value("neg", 1, |operands| operands[0].checked_neg()));
+        instructions.allow_nesting = true;
+        for source in [SAMPLE, "mul(123,4567)mul(mul(1,2),neg(3))don't(do()", ""] {
+            let expected: Vec<Spanned> = Lexer::new(source, &instructions).collect();
+            for chunk_size in 1..=source.len() + 1 {
+                let streamed: Vec<Spanned> = StreamLexer::new(source.as_bytes(), &instructions, chunk_size)
+                    .map(|spanned| spanned.unwrap())
+                    .collect();
+                assert_eq!(streamed, expected, "{} with chunk size {}", source, chunk_size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_memory_is_bounded() {
+        let instructions = InstructionSet::default();
+        let source = "mul(12,345)?don't()".repeat(10_000);
+        let mut lexer = StreamLexer::new(source.as_bytes(), &instructions, 64);
+        let mut count = 0;
+        while let Some(spanned) = lexer.next() {
+            spanned.unwrap();
+            count += 1;
+            assert!(lexer.buffer.len() <= 64 + "mul(123,123)".len());
+        }
+        assert_eq!(count, 20_000);
+    }
+
+    #[test]
+    fn test_stream_memory_is_bounded_with_nesting() {
+        let mut instructions = InstructionSet::default();
+        instructions.allow_nesting = true;
+        let source = format!("{}do()", "mul(".repeat(1_000)).repeat(20);
+        let mut lexer = StreamLexer::new(source.as_bytes(), &instructions, 64);
+        let mut count = 0;
+        while let Some(spanned) = lexer.next() {
+            assert_eq!(spanned.unwrap().token.name, "do");
+            count += 1;
+            assert!(lexer.buffer.len() <= 64 + "mul(".len() * (MAX_NESTING + 1) + "do()".len());
+        }
+        assert_eq!(count, 20);
+    }
+}
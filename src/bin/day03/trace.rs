@@ -0,0 +1,133 @@
+use std::ops::Range;
+use crate::lexer::Token;
+
+const RESET: &str = "\x1b[0m";
+const COUNTED: &str = "\x1b[1;32m";
+const IGNORED: &str = "\x1b[2;9m";
+const CONTROL: &str = "\x1b[1;36m";
+const DISABLED: &str = "\x1b[2m";
+
+/**
+ * One executed instruction. `enabled` is whether instructions were enabled when it was reached,
+ * and `ignored` is set for a value instruction skipped because they weren't. `value` is what it
+ * added to the total, and `enabled_after` is whether instructions are enabled once it has run.
+ */
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct TraceStep {
+    pub span: Range<usize>,
+    pub token: Token,
+    pub enabled: bool,
+    pub ignored: bool,
+    pub value: Option<i64>,
+    pub total: i128,
+    pub enabled_after: bool
+}
+
+pub fn trace_table(steps: &[TraceStep]) -> String {
+    let width = steps.iter().map(|step| step.token.to_string().len()).max().unwrap_or(0).max("Instruction".len());
+    let mut table = format!("{:>8} | {:<width$} | Enabled | {:>8} | {:>10}\n", "Offset", "Instruction", "Value", "Total");
+    steps.iter().for_each(|step| {
+        let value = step.value.map(|value| value.to_string()).unwrap_or_default();
+        let enabled = if step.enabled { "yes" } else { "no" };
+        table.push_str(&format!(
+            "{:>8} | {:<width$} | {:<7} | {:>8} | {:>10}\n",
+            step.span.start, step.token.to_string(), enabled, value, step.total
+        ));
+    });
+    table
+}
+
+/**
+ * Renders the source with ANSI colors: instructions that added to the total are highlighted,
+ * control instructions stand out in another color, and everything that was read while
+ * instructions were disabled is dimmed, with ignored instructions struck through.
+ */
+pub fn annotate(source: &str, steps: &[TraceStep]) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut push = |text: &str, style: Option<&str>| {
+        if text.is_empty() {
+            return;
+        }
+        match style {
+            Some(style) => {
+                output.push_str(style);
+                output.push_str(text);
+                output.push_str(RESET);
+            }
+            None => output.push_str(text)
+        }
+    };
+    let plain_style = |enabled: bool| if enabled { None } else { Some(DISABLED) };
+
+    let mut position = 0;
+    let mut enabled = true;
+    for step in steps {
+        push(&source[position..step.span.start], plain_style(enabled));
+        let style = match (step.value, step.ignored) {
+            (Some(_), _) => COUNTED,
+            (None, true) => IGNORED,
+            (None, false) => CONTROL
+        };
+        push(&source[step.span.clone()], Some(style));
+        position = step.span.end;
+        enabled = step.enabled_after;
+    }
+    push(&source[position..], plain_style(enabled));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::InstructionSet;
+    use crate::interpreter::Interpreter;
+    use crate::lexer::Lexer;
+
+    const SAMPLE: &str = "xmul(2,4)&don't()_mul(5,5)do()?mul(8,5))";
+
+    fn trace(source: &str, conditional: bool) -> Vec<TraceStep> {
+        let instructions = InstructionSet::default();
+        Interpreter::new(&instructions, conditional, None)
+            .trace(Lexer::new(source, &instructions))
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_trace() {
+        let steps = trace(SAMPLE, true);
+        let summary: Vec<(usize, bool, bool, Option<i64>, i128)> = steps.iter()
+            .map(|step| (step.span.start, step.enabled, step.ignored, step.value, step.total))
+            .collect();
+        assert_eq!(summary, vec![
+            (1, true, false, Some(8), 8),
+            (10, true, false, None, 8),
+            (18, false, true, None, 8),
+            (26, false, false, None, 8),
+            (31, true, false, Some(40), 48)
+        ]);
+        assert!(trace(SAMPLE, false).iter().all(|step| step.enabled));
+    }
+
+    #[test]
+    fn test_trace_table() {
+        let expected = concat!(
+            "  Offset | Instruction | Enabled |    Value |      Total\n",
+            "       1 | mul(2,4)    | yes     |        8 |          8\n",
+            "      10 | don't()     | yes     |          |          8\n",
+            "      18 | mul(5,5)    | no      |          |          8\n",
+            "      26 | do()        | no      |          |          8\n",
+            "      31 | mul(8,5)    | yes     |       40 |         48\n"
+        );
+        assert_eq!(trace_table(&trace(SAMPLE, true)), expected);
+    }
+
+    #[test]
+    fn test_annotate() {
+        let expected = format!(
+            "x{COUNTED}mul(2,4){RESET}&{CONTROL}don't(){RESET}{DISABLED}_{RESET}{IGNORED}mul(5,5){RESET}{CONTROL}do(){RESET}?{COUNTED}mul(8,5){RESET})"
+        );
+        assert_eq!(annotate(SAMPLE, &trace(SAMPLE, true)), expected);
+        assert_eq!(annotate("no instructions", &[]), "no instructions");
+    }
+}
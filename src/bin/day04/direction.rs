@@ -0,0 +1,102 @@
+use std::fmt::{Display, Formatter, Result};
+
+/// One of the eight ways a word can be read, with `y` growing downwards
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Debug)]
+pub enum Direction {
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+    Up,
+    UpRight
+}
+
+impl Direction {
+    /// Every direction, turning clockwise from right
+    pub const ALL: [Direction; 8] = [
+        Direction::Right,
+        Direction::DownRight,
+        Direction::Down,
+        Direction::DownLeft,
+        Direction::Left,
+        Direction::UpLeft,
+        Direction::Up,
+        Direction::UpRight
+    ];
+
+    pub fn iter() -> impl Iterator<Item = Direction> {
+        Self::ALL.into_iter()
+    }
+
+    /// How far one step moves in `x` and `y`
+    pub fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::Right => (1, 0),
+            Direction::DownRight => (1, 1),
+            Direction::Down => (0, 1),
+            Direction::DownLeft => (-1, 1),
+            Direction::Left => (-1, 0),
+            Direction::UpLeft => (-1, -1),
+            Direction::Up => (0, -1),
+            Direction::UpRight => (1, -1)
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::Right => "right",
+            Direction::DownRight => "down right",
+            Direction::Down => "down",
+            Direction::DownLeft => "down left",
+            Direction::Left => "left",
+            Direction::UpLeft => "up left",
+            Direction::Up => "up",
+            Direction::UpRight => "up right"
+        }
+    }
+
+    /// The position `distance` steps away from `x`, `y`
+    pub fn step(self, (x, y): (i32, i32), distance: i32) -> (i32, i32) {
+        let (x_offset, y_offset) = self.offset();
+        (x + distance * x_offset, y + distance * y_offset)
+    }
+
+    /// Every position from `start` onwards in this direction, starting with `start` itself
+    pub fn walk(self, start: (i32, i32)) -> impl Iterator<Item = (i32, i32)> {
+        let (x_offset, y_offset) = self.offset();
+        std::iter::successors(Some(start), move |(x, y)| Some((x + x_offset, y + y_offset)))
+    }
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset() {
+        assert_eq!(Direction::Right.offset(), (1, 0));
+        assert_eq!(Direction::DownRight.offset(), (1, 1));
+        assert_eq!(Direction::Down.offset(), (0, 1));
+        assert_eq!(Direction::DownLeft.offset(), (-1, 1));
+        assert_eq!(Direction::Left.offset(), (-1, 0));
+        assert_eq!(Direction::UpLeft.offset(), (-1, -1));
+        assert_eq!(Direction::Up.offset(), (0, -1));
+        assert_eq!(Direction::UpRight.offset(), (1, -1));
+    }
+
+    #[test]
+    fn test_walk() {
+        let cells: Vec<(i32, i32)> = Direction::UpLeft.walk((2, 3)).take(3).collect();
+        assert_eq!(cells, vec![(2, 3), (1, 2), (0, 1)]);
+        assert_eq!(Direction::DownLeft.step((2, 3), 2), (0, 5));
+        assert_eq!(Direction::DownLeft.to_string(), "down left");
+    }
+}
@@ -0,0 +1,231 @@
+use std::fmt::{Display, Formatter};
+use itertools::Itertools;
+use crate::direction::Direction;
+use crate::word_search::{WordMatch, WordSearch};
+
+/// How many times to start over before giving up on a puzzle
+const ATTEMPTS: usize = 100;
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum GenerationError {
+    /// The word can't fit in the grid in any direction
+    TooLong(String),
+    /// Every attempt either ran out of room or couldn't avoid matches that weren't placed
+    NoPuzzle
+}
+
+impl Display for GenerationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GenerationError::TooLong(word) => write!(f, "{} is too long to fit in the grid", word),
+            GenerationError::NoPuzzle => write!(f, "No puzzle found after {} attempts", ATTEMPTS)
+        }
+    }
+}
+
+/**
+ * A generated grid and its answer key. Every word in the grid is one of `answers`, where
+ * `word` indexes into `words`, and there are no other matches.
+ */
+pub struct Puzzle {
+    pub words: Vec<String>,
+    pub grid: Vec<Vec<char>>,
+    pub answers: Vec<WordMatch>
+}
+
+impl Display for Puzzle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for row in self.grid.iter() {
+            writeln!(f, "{}", row.iter().collect::<String>())?;
+        }
+        writeln!(f)?;
+        for answer in self.answers.iter() {
+            writeln!(f, "{} at ({}, {}) going {}", self.words[answer.word], answer.x, answer.y, answer.direction)?;
+        }
+        Ok(())
+    }
+}
+
+/// A grid being built, where `None` is a cell with no letter yet
+type Cells = Vec<Vec<Option<char>>>;
+
+/// SplitMix64, which is plenty for shuffling letters and works from any seed
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for index in (1..items.len()).rev() {
+            items.swap(index, self.below(index + 1));
+        }
+    }
+}
+
+/**
+ * Builds `width` by `height` word searches. Each word is placed in a random direction, crossing
+ * earlier words only where the letters agree, and the remaining cells are filled from the
+ * letters of the words, so the filler looks like the words without ever completing one.
+ *
+ * A word that reads the same backwards, or words that contain each other or each other's reverse,
+ * always match more than once, so no puzzle can be made for them.
+ */
+pub struct Generator {
+    width: usize,
+    height: usize,
+    random: Random
+}
+
+impl Generator {
+    pub fn new(width: usize, height: usize, seed: u64) -> Self {
+        Self { width, height, random: Random(seed) }
+    }
+
+    /// A puzzle with each word placed once, in the order given. A word can be given more than once
+    pub fn generate(&mut self, words: &[&str]) -> Result<Puzzle, GenerationError> {
+        let words: Vec<Vec<char>> = words.iter().map(|word| word.chars().collect()).collect();
+        if let Some(word) = words.iter().find(|word| word.len() > self.width.max(self.height)) {
+            return Err(GenerationError::TooLong(word.iter().collect()));
+        }
+        let alphabet: Vec<char> = words.iter().flatten().copied().unique().collect();
+        for _ in 0..ATTEMPTS {
+            let Some((cells, answers)) = self.place(&words) else { continue };
+            let Some(grid) = self.fill(cells, &words, &alphabet) else { continue };
+            let puzzle = Puzzle {
+                words: words.iter().map(|word| word.iter().collect()).collect(),
+                grid,
+                answers
+            };
+            if puzzle.verify() {
+                return Ok(puzzle);
+            }
+        }
+        Err(GenerationError::NoPuzzle)
+    }
+
+    fn place(&mut self, words: &[Vec<char>]) -> Option<(Cells, Vec<WordMatch>)> {
+        let (width, height) = (self.width, self.height);
+        let mut cells = vec![vec![None; width]; height];
+        let mut answers = Vec::with_capacity(words.len());
+        for (index, word) in words.iter().enumerate() {
+            let placements: Vec<(usize, usize, Direction)> = Direction::iter()
+                .flat_map(|direction| (0..height).flat_map(move |y| (0..width).map(move |x| (x, y, direction))))
+                .filter(|(x, y, direction)| {
+                    let line: Vec<Option<Option<char>>> = direction.walk((*x as i32, *y as i32))
+                        .take(word.len())
+                        .map(|position| get(&cells, position))
+                        .collect();
+                    // A word laid entirely over letters already placed would just be found twice
+                    line.iter().zip(word.iter()).all(|(cell, char)| cell.is_some_and(|cell| cell.is_none_or(|cell| cell == *char)))
+                        && line.contains(&Some(None))
+                })
+                .collect();
+            if placements.is_empty() {
+                return None;
+            }
+            let (x, y, direction) = placements[self.random.below(placements.len())];
+            direction.walk((x as i32, y as i32)).zip(word.iter())
+                .for_each(|((x, y), char)| cells[y as usize][x as usize] = Some(*char));
+            answers.push(WordMatch { word: index, x, y, direction });
+        }
+        Some((cells, answers))
+    }
+
+    /**
+     * Fills every empty cell with a letter that doesn't complete a word. A word can only be
+     * completed by the last of its cells to be filled, so checking the words through each cell
+     * as it is filled finds every accidental match.
+     */
+    fn fill(&mut self, mut cells: Cells, words: &[Vec<char>], alphabet: &[char]) -> Option<Vec<Vec<char>>> {
+        let mut letters = alphabet.to_vec();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if cells[y][x].is_some() {
+                    continue;
+                }
+                self.random.shuffle(&mut letters);
+                let letter = letters.iter().copied().find(|letter| {
+                    cells[y][x] = Some(*letter);
+                    !completes_word(&cells, words, (x as i32, y as i32))
+                });
+                cells[y][x] = Some(letter?);
+            }
+        }
+        Some(cells.into_iter().map(|row| row.into_iter().flatten().collect()).collect())
+    }
+}
+
+impl Puzzle {
+    /// Whether searching the grid finds exactly the answers and nothing else
+    pub fn verify(&self) -> bool {
+        let unique: Vec<&str> = self.words.iter().map(String::as_str).unique().collect();
+        let found = WordSearch::new(&unique).find_all(&self.grid).into_iter()
+            .map(|found| (unique[found.word], found.x, found.y, found.direction))
+            .sorted();
+        let expected = self.answers.iter()
+            .map(|answer| (self.words[answer.word].as_str(), answer.x, answer.y, answer.direction))
+            .sorted();
+        found.eq(expected)
+    }
+}
+
+fn get(cells: &[Vec<Option<char>>], (x, y): (i32, i32)) -> Option<Option<char>> {
+    if x < 0 || y < 0 {
+        return None;
+    }
+    cells.get(y as usize)?.get(x as usize).copied()
+}
+
+/// Whether any word reads in full through `position`
+fn completes_word(cells: &[Vec<Option<char>>], words: &[Vec<char>], position: (i32, i32)) -> bool {
+    words.iter().any(|word| {
+        Direction::iter().any(|direction| {
+            (0..word.len() as i32).any(|index| {
+                let start = direction.step(position, -index);
+                direction.walk(start).zip(word.iter()).all(|(cell, char)| get(cells, cell) == Some(Some(*char)))
+            })
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_places_every_word_once() {
+        let words = ["XMAS", "XMAS", "MASS", "SAXM"];
+        for seed in 0..20 {
+            let puzzle = Generator::new(8, 6, seed).generate(&words).unwrap();
+            assert_eq!(puzzle.grid.len(), 6);
+            assert!(puzzle.grid.iter().all(|row| row.len() == 8 && row.iter().all(|char| "XMAS".contains(*char))));
+            assert_eq!(puzzle.answers.len(), words.len());
+            assert_eq!(WordSearch::new(&["XMAS", "MASS", "SAXM"]).counts(&puzzle.grid), vec![2, 1, 1]);
+        }
+    }
+
+    #[test]
+    fn test_generate_is_repeatable() {
+        let first = Generator::new(10, 10, 42).generate(&["XMAS"]).unwrap();
+        let second = Generator::new(10, 10, 42).generate(&["XMAS"]).unwrap();
+        assert_eq!(first.to_string(), second.to_string());
+    }
+
+    #[test]
+    fn test_generate_errors() {
+        assert_eq!(Generator::new(3, 2, 0).generate(&["XMAS"]).err(), Some(GenerationError::TooLong("XMAS".to_string())));
+        // A palindrome is always found reading both ways, as is a word along with its reverse
+        assert_eq!(Generator::new(5, 5, 0).generate(&["ABA"]).err(), Some(GenerationError::NoPuzzle));
+        assert_eq!(Generator::new(5, 5, 0).generate(&["XMAS", "SAMX"]).err(), Some(GenerationError::NoPuzzle));
+    }
+}
@@ -1,15 +1,70 @@
-use std::f32::consts::PI;
+use std::time::{SystemTime, UNIX_EPOCH};
+use generator::Generator;
+use pattern::Pattern;
+use render::{render, Style};
+use word_search::WordSearch;
+
+mod direction;
+mod generator;
+mod pattern;
+mod render;
+#[cfg(test)]
+mod test_support;
+mod word_search;
 
 fn main() {
+    // `--generate=WORD,WORD,...` prints a new puzzle hiding those words and its answer key
+    // instead, sized by `--size=<width>x<height>` and repeatable with `--seed=<n>`
+    let flags: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(words) = flags.iter().find_map(|flag| flag.strip_prefix("--generate=")) {
+        generate(words, &flags);
+        return;
+    }
     let grid = parse_input("./src/bin/day04/input.txt");
+    // `--words=WORD,WORD,...` counts each of those words as well. `--show` draws the grid with
+    // only the letters of each part's matches, and `--show=ansi` highlights them in color
+    let style = flags.iter()
+        .find_map(|flag| match flag.as_str() {
+            "--show" => Some(Style::Text),
+            _ => flag.strip_prefix("--show=").map(Style::from)
+        });
+    if let Some(words) = flags.iter().find_map(|flag| flag.strip_prefix("--words=")) {
+        let words: Vec<&str> = words.split(',').collect();
+        let counts = WordSearch::new(&words).counts(&grid);
+        words.iter().zip(counts).for_each(|(word, count)| println!("{}: {}", word, count));
+    }
     // Part 1
+    if let Some(style) = style {
+        println!("{}", render(&grid, xmas_cells(&grid), style));
+    }
     let xmas_count = count_of_xmas(&grid);
     println!("XMAS count: {}", xmas_count);
     // Part 2
+    if let Some(style) = style {
+        println!("{}", render(&grid, x_mas_cells(&grid), style));
+    }
     let x_mas_count = count_of_x_mas(&grid);
     println!("X MAS count: {}", x_mas_count);
 }
 
+fn generate(words: &str, flags: &[String]) {
+    let words: Vec<&str> = words.split(',').collect();
+    let (width, height) = flags.iter()
+        .find_map(|flag| flag.strip_prefix("--size="))
+        .map(|size| {
+            let (width, height) = size.split_once('x').unwrap_or_else(|| panic!("Invalid size. {} is not <width>x<height>.", size));
+            let parse = |value: &str| value.parse::<usize>().unwrap_or_else(|_| panic!("Invalid input. {} is not a number.", value));
+            (parse(width), parse(height))
+        })
+        .unwrap_or((10, 10));
+    let seed = flags.iter()
+        .find_map(|flag| flag.strip_prefix("--seed="))
+        .map(|seed| seed.parse::<u64>().unwrap_or_else(|_| panic!("Invalid input. {} is not a number.", seed)))
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos() as u64));
+    let puzzle = Generator::new(width, height, seed).generate(&words).unwrap_or_else(|error| panic!("{}", error));
+    print!("{}", puzzle);
+}
+
 fn parse_input(file_path: &str) -> Vec<Vec<char>> {
     std::fs::read_to_string(file_path)
         .expect(&format!("Error reading from file path: {}", file_path))
@@ -19,68 +74,29 @@ fn parse_input(file_path: &str) -> Vec<Vec<char>> {
         .collect()
 }
 
-fn get_offset(direction: u8) -> (i32, i32) {
-    let x = ((f32::from(direction) * PI / 4f32).cos().round()) as i32;
-    let y = ((f32::from(direction) * PI / 4f32).sin().round()) as i32;
-    (x, y)
+fn count_of_xmas(grid: &[Vec<char>]) -> usize {
+    WordSearch::new(&["XMAS"]).find_all(grid).len()
 }
 
-fn count_of_xmas(grid: &Vec<Vec<char>>) -> usize {
-    let word: Vec<char> = "XMAS".chars().collect();
-    // For every row
-    grid.iter().enumerate().map(|(y, row)| {
-        // For every character in the row
-        row.iter().enumerate().map(|(x, _)| {
-            // Check all 8 directions
-            //       5   6   7
-            //         \ | /
-            //      4 ---*--- 0
-            //         / | \
-            //       3   2   1
-            (0..8u8).filter(|direction| {
-                // Check all characters in that direction
-                (0..word.len()).all(|index| {
-                    // Calculate the change in x and y
-                    // https://en.wikipedia.org/wiki/Unit_circle
-                    let (x_offset, y_offset) = get_offset(*direction);
-                    let delta_x = index as i32 * x_offset;
-                    let delta_y = index as i32 * y_offset;
-                    let new_x = (x as i32 + delta_x) as usize;
-                    let new_y = (y as i32 + delta_y) as usize;
-                    // check bounds
-                    (0..row.len()).contains(&new_x) && (0..grid.len()).contains(&new_y) &&
-                    // check the search word
-                    grid[new_y][new_x] == word[index]
-                })
-            }).count()
-        }).sum::<usize>()
-    }).sum()
+fn count_of_x_mas(grid: &[Vec<char>]) -> usize {
+    x_mas_pattern().count(grid)
 }
 
-fn count_of_x_mas(grid: &Vec<Vec<char>>) -> usize {
-    // 2D sliding window
-    (0..grid.len() - 2).map(|y| {
-        (0..grid[y].len() - 2).filter(|x| {
-            grid[y+1][x+1] == 'A' &&
-            ((grid[y][*x] == 'M' && grid[y+2][x+2] == 'S') || (grid[y][*x] == 'S' && grid[y+2][x+2] == 'M')) &&
-            ((grid[y+2][*x] == 'M' && grid[y][x+2] == 'S') || (grid[y+2][*x] == 'S' && grid[y][x+2] == 'M'))
-        }).count()
-    }).sum()
+// Two MAS crossing on the A, in any of the four ways they can be turned
+fn x_mas_pattern() -> Pattern {
+    Pattern::from("M.S/.A./M.S")
 }
 
-// For debugging
-fn _to_string(direction: u8) -> String {
-    match direction {
-        0 => "right".to_string(),
-        1 => "down right".to_string(),
-        2 => "down".to_string(),
-        3 => "down left".to_string(),
-        4 => "left".to_string(),
-        5 => "up left".to_string(),
-        6 => "up".to_string(),
-        7 => "up right".to_string(),
-        _ => "invalid direction".to_string()
-    }
+/// Every cell that is part of some XMAS
+fn xmas_cells(grid: &[Vec<char>]) -> Vec<(usize, usize)> {
+    let search = WordSearch::new(&["XMAS"]);
+    search.find_all(grid).iter().flat_map(|found| search.cells(found)).collect()
+}
+
+/// Every cell that is part of some X-MAS
+fn x_mas_cells(grid: &[Vec<char>]) -> Vec<(usize, usize)> {
+    let pattern = x_mas_pattern();
+    pattern.find_all(grid).iter().flat_map(|found| pattern.cells(found)).collect()
 }
 
 #[cfg(test)]
@@ -141,14 +157,33 @@ mod tests {
     }
 
     #[test]
-    fn test_get_offset() {
-        assert_eq!(get_offset(0), (1, 0));
-        assert_eq!(get_offset(1), (1, 1));
-        assert_eq!(get_offset(2), (0, 1));
-        assert_eq!(get_offset(3), (-1, 1));
-        assert_eq!(get_offset(4), (-1, 0));
-        assert_eq!(get_offset(5), (-1, -1));
-        assert_eq!(get_offset(6), (0, -1));
-        assert_eq!(get_offset(7), (1, -1));
+    fn test_render_matches() {
+        let grid = parse_input("./src/bin/day04/sample_input.txt");
+        let xmas = concat!(
+            "....XXMAS.\n",
+            ".SAMXMS...\n",
+            "...S..A...\n",
+            "..A.A.MS.X\n",
+            "XMASAMX.MM\n",
+            "X.....XA.A\n",
+            "S.S.S.S.SS\n",
+            ".A.A.A.A.A\n",
+            "..M.M.M.MM\n",
+            ".X.X.XMASX\n"
+        );
+        assert_eq!(render(&grid, xmas_cells(&grid), Style::Text), xmas);
+        let x_mas = concat!(
+            ".M.S......\n",
+            "..A..MSMS.\n",
+            ".M.S.MAA..\n",
+            "..A.ASMSM.\n",
+            ".M.S.M....\n",
+            "..........\n",
+            "S.S.S.S.S.\n",
+            ".A.A.A.A..\n",
+            "M.M.M.M.M.\n",
+            "..........\n"
+        );
+        assert_eq!(render(&grid, x_mas_cells(&grid), Style::Text), x_mas);
     }
-}
\ No newline at end of file
+}
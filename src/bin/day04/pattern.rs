@@ -0,0 +1,175 @@
+/// A placement of a pattern, with `x`, `y` the top left corner of the oriented pattern
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub struct PatternMatch {
+    pub x: usize,
+    pub y: usize,
+    pub orientation: usize
+}
+
+/**
+ * A small 2D template where `None` matches any letter. Written as rows separated by `/` with
+ * `.` for a wildcard, e.g. `M.S/.A./M.S`.
+ */
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Pattern {
+    cells: Vec<Vec<Option<char>>>
+}
+
+impl Pattern {
+    pub fn width(&self) -> usize {
+        self.cells.first().map_or(0, |row| row.len())
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Quarter turn clockwise
+    pub fn rotate(&self) -> Self {
+        let cells = (0..self.width())
+            .map(|x| (0..self.height()).rev().map(|y| self.cells[y][x]).collect())
+            .collect();
+        Self { cells }
+    }
+
+    /// Mirror image from left to right
+    pub fn reflect(&self) -> Self {
+        let cells = self.cells.iter()
+            .map(|row| row.iter().rev().copied().collect())
+            .collect();
+        Self { cells }
+    }
+
+    /**
+     * Every rotation and reflection of the pattern. Symmetric patterns look the same in some
+     * of them, so those are only included once, otherwise each match would be counted again for
+     * every orientation it is identical under.
+     */
+    pub fn orientations(&self) -> Vec<Pattern> {
+        let mut orientations: Vec<Pattern> = Vec::with_capacity(8);
+        let mut current = self.clone();
+        for _ in 0..4 {
+            for candidate in [current.clone(), current.reflect()] {
+                if !orientations.contains(&candidate) {
+                    orientations.push(candidate);
+                }
+            }
+            current = current.rotate();
+        }
+        orientations
+    }
+
+    /// Matches in any orientation, where `orientation` indexes into `orientations`
+    pub fn find_all(&self, grid: &[Vec<char>]) -> Vec<PatternMatch> {
+        self.orientations().iter().enumerate()
+            .flat_map(|(orientation, pattern)| {
+                pattern.placements(grid)
+                    .filter(|(x, y)| pattern.matches_at(grid, *x, *y))
+                    .map(move |(x, y)| PatternMatch { x, y, orientation })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    pub fn count(&self, grid: &[Vec<char>]) -> usize {
+        self.find_all(grid).len()
+    }
+
+    /// Where each letter of a match is, leaving out the wildcards
+    pub fn cells(&self, found: &PatternMatch) -> Vec<(usize, usize)> {
+        let pattern = &self.orientations()[found.orientation];
+        pattern.cells.iter().enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().filter(|(_, cell)| cell.is_some()).map(move |(x, _)| (x, y)))
+            .map(|(x, y)| (found.x + x, found.y + y))
+            .collect()
+    }
+
+    fn placements<'a>(&self, grid: &'a [Vec<char>]) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let (width, height) = (self.width(), self.height());
+        (0..(grid.len() + 1).saturating_sub(height))
+            .flat_map(move |y| (0..(grid[y].len() + 1).saturating_sub(width)).map(move |x| (x, y)))
+    }
+
+    fn matches_at(&self, grid: &[Vec<char>], x: usize, y: usize) -> bool {
+        self.cells.iter().enumerate().all(|(row_index, row)| {
+            row.iter().enumerate().all(|(column, cell)| {
+                match (cell, grid.get(y + row_index).and_then(|grid_row| grid_row.get(x + column))) {
+                    (None, Some(_)) => true,
+                    (Some(expected), Some(actual)) => expected == actual,
+                    (_, None) => false
+                }
+            })
+        })
+    }
+}
+
+impl From<&str> for Pattern {
+    fn from(value: &str) -> Self {
+        let cells: Vec<Vec<Option<char>>> = value.split('/')
+            .map(|row| row.chars().map(|char| if char == '.' { None } else { Some(char) }).collect())
+            .collect();
+        if cells.iter().any(|row| row.len() != cells[0].len()) {
+            panic!("Invalid pattern. {} has rows of different lengths.", value);
+        }
+        Self { cells }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::grid;
+
+    #[test]
+    fn test_rotate_and_reflect() {
+        let pattern = Pattern::from("AB./..C");
+        assert_eq!(pattern.rotate(), Pattern::from(".A/.B/C."));
+        assert_eq!(pattern.reflect(), Pattern::from(".BA/C.."));
+        assert_eq!(pattern.rotate().rotate().rotate().rotate(), pattern);
+    }
+
+    #[test]
+    fn test_orientations_skip_symmetric_duplicates() {
+        assert_eq!(Pattern::from("M.S/.A./M.S").orientations().len(), 4);
+        assert_eq!(Pattern::from("A.A/.A./A.A").orientations().len(), 1);
+        assert_eq!(Pattern::from("AB/C.").orientations().len(), 8);
+        assert_eq!(Pattern::from("XMAS").orientations().len(), 4);
+    }
+
+    #[test]
+    fn test_find_all() {
+        let grid = grid(&[
+            "M.S.",
+            ".A..",
+            "M.SX",
+            "...."
+        ]);
+        assert_eq!(Pattern::from("M.S/.A./M.S").find_all(&grid), vec![PatternMatch { x: 0, y: 0, orientation: 0 }]);
+        assert_eq!(Pattern::from("SX").count(&grid), 1);
+        assert_eq!(Pattern::from("XS").count(&grid), 1);
+        assert_eq!(Pattern::from("X/S").count(&grid), 1);
+        assert_eq!(Pattern::from("XXXXX").count(&grid), 0);
+    }
+
+    #[test]
+    fn test_cells() {
+        let grid = grid(&[
+            "....",
+            ".S.S",
+            "..A.",
+            ".M.M"
+        ]);
+        let pattern = Pattern::from("M.S/.A./M.S");
+        let found = pattern.find_all(&grid);
+        assert_eq!(found.len(), 1);
+        let mut cells = pattern.cells(&found[0]);
+        cells.sort();
+        assert_eq!(cells, vec![(1, 1), (1, 3), (2, 2), (3, 1), (3, 3)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "rows of different lengths")]
+    fn test_ragged_pattern() {
+        let _ = Pattern::from("AB/C");
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::HashSet;
+
+const RESET: &str = "\x1b[0m";
+const MATCHED: &str = "\x1b[1;33m";
+const UNMATCHED: &str = "\x1b[2m";
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Style {
+    Text,
+    Ansi
+}
+
+impl From<&str> for Style {
+    fn from(value: &str) -> Self {
+        match value {
+            "text" => Style::Text,
+            "ansi" => Style::Ansi,
+            _ => panic!("Invalid style. {} is not text or ansi.", value)
+        }
+    }
+}
+
+/**
+ * Draws the grid with only the letters at `cells` shown and every other letter replaced by `.`,
+ * like the puzzle's own illustrations. With ANSI colors the shown letters are highlighted and the
+ * rest dimmed.
+ */
+pub fn render(grid: &[Vec<char>], cells: impl IntoIterator<Item = (usize, usize)>, style: Style) -> String {
+    let cells: HashSet<(usize, usize)> = cells.into_iter().collect();
+    let mut output = String::with_capacity(grid.iter().map(|row| row.len() + 1).sum());
+    for (y, row) in grid.iter().enumerate() {
+        for (x, char) in row.iter().enumerate() {
+            let matched = cells.contains(&(x, y));
+            match (style, matched) {
+                (Style::Text, true) => output.push(*char),
+                (Style::Text, false) => output.push('.'),
+                (Style::Ansi, true) => output.push_str(&format!("{}{}{}", MATCHED, char, RESET)),
+                (Style::Ansi, false) => output.push_str(&format!("{}.{}", UNMATCHED, RESET))
+            }
+        }
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let grid = vec![vec!['A', 'B'], vec!['C', 'D']];
+        assert_eq!(render(&grid, [(1, 0), (0, 1)], Style::Text), ".B\nC.\n");
+        assert_eq!(render(&grid, [], Style::Text), "..\n..\n");
+        assert_eq!(
+            render(&grid, [(0, 0)], Style::Ansi),
+            format!("{MATCHED}A{RESET}{UNMATCHED}.{RESET}\n{UNMATCHED}.{RESET}{UNMATCHED}.{RESET}\n")
+        );
+    }
+}
@@ -0,0 +1,4 @@
+/// A grid from its rows, for tests that spell out small grids
+pub fn grid(rows: &[&str]) -> Vec<Vec<char>> {
+    rows.iter().map(|row| row.chars().collect()).collect()
+}
@@ -0,0 +1,292 @@
+use std::collections::VecDeque;
+use crate::direction::Direction;
+
+/// A word found in the grid, starting at `x`, `y` and reading in `direction`
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub struct WordMatch {
+    pub word: usize,
+    pub x: usize,
+    pub y: usize,
+    pub direction: Direction
+}
+
+#[derive(Default)]
+struct Node {
+    // Few letters follow any one prefix, so a list is quicker to search than a map
+    children: Vec<(char, usize)>,
+    fail: usize,
+    // Indices of every word ending here, including those ending at the fail links
+    outputs: Vec<usize>
+}
+
+impl Node {
+    fn child(&self, char: char) -> Option<usize> {
+        self.children.iter().find(|(child_char, _)| *child_char == char).map(|(_, child)| *child)
+    }
+}
+
+/**
+ * Searches a grid for many words at once with an Aho-Corasick automaton. Every row, column and
+ * diagonal is read once in each direction, so the work depends on the size of the grid and the
+ * number of matches rather than on how many words there are. A single word is quicker to check
+ * against each cell directly, so that is searched for without the automaton.
+ */
+pub struct WordSearch {
+    words: Vec<Vec<char>>,
+    nodes: Vec<Node>
+}
+
+impl WordSearch {
+    pub fn new(words: &[&str]) -> Self {
+        let words: Vec<Vec<char>> = words.iter().map(|word| word.chars().collect()).collect();
+        let mut nodes = vec![Node::default()];
+        words.iter().enumerate()
+            .filter(|(_, word)| !word.is_empty())
+            .for_each(|(index, word)| {
+                let end = word.iter().fold(0, |node, char| {
+                    match nodes[node].child(*char) {
+                        Some(child) => child,
+                        None => {
+                            nodes.push(Node::default());
+                            let child = nodes.len() - 1;
+                            nodes[node].children.push((*char, child));
+                            child
+                        }
+                    }
+                });
+                nodes[end].outputs.push(index);
+            });
+
+        // Breadth first so every fail link points at a node that is already finished
+        let mut queue: VecDeque<usize> = nodes[0].children.iter().map(|(_, child)| *child).collect();
+        while let Some(node) = queue.pop_front() {
+            let children = nodes[node].children.clone();
+            for (char, child) in children {
+                let mut fail = nodes[node].fail;
+                while fail != 0 && nodes[fail].child(char).is_none() {
+                    fail = nodes[fail].fail;
+                }
+                let fail = nodes[fail].child(char).filter(|fail| *fail != child).unwrap_or(0);
+                nodes[child].fail = fail;
+                let inherited = nodes[fail].outputs.clone();
+                nodes[child].outputs.extend(inherited);
+                queue.push_back(child);
+            }
+        }
+        Self { words, nodes }
+    }
+
+    pub fn find_all(&self, grid: &[Vec<char>]) -> Vec<WordMatch> {
+        let mut matches = Vec::new();
+        if self.words.len() == 1 {
+            self.search_cells(grid, &mut matches);
+            return matches;
+        }
+        for direction in Direction::iter() {
+            for start in line_starts(grid, direction) {
+                self.search_line(grid, start, direction, &mut matches);
+            }
+        }
+        matches
+    }
+
+    /// How many times each word appears, in the order the words were given
+    pub fn counts(&self, grid: &[Vec<char>]) -> Vec<usize> {
+        let mut counts = vec![0; self.words.len()];
+        self.find_all(grid).iter().for_each(|found| counts[found.word] += 1);
+        counts
+    }
+
+    /// Where each letter of a found word is, from its first letter to its last
+    pub fn cells(&self, found: &WordMatch) -> Vec<(usize, usize)> {
+        let start = (found.x as i32, found.y as i32);
+        found.direction.walk(start)
+            .take(self.words[found.word].len())
+            .map(|(x, y)| (x as usize, y as usize))
+            .collect()
+    }
+
+    /// Checks the only word against every cell and direction, which beats the automaton for one word
+    fn search_cells(&self, grid: &[Vec<char>], matches: &mut Vec<WordMatch>) {
+        let word = &self.words[0];
+        if word.is_empty() {
+            return;
+        }
+        for (y, row) in grid.iter().enumerate() {
+            for (x, char) in row.iter().enumerate() {
+                if *char != word[0] {
+                    continue;
+                }
+                for direction in Direction::iter() {
+                    let found = word.iter().enumerate().skip(1).all(|(index, char)| {
+                        let (x, y) = direction.step((x as i32, y as i32), index as i32);
+                        in_bounds(grid, (x, y)) && grid[y as usize][x as usize] == *char
+                    });
+                    if found {
+                        matches.push(WordMatch { word: 0, x, y, direction });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads a line across the grid's bounding box, starting over past any cell a short row lacks
+    fn search_line(&self, grid: &[Vec<char>], start: (usize, usize), direction: Direction, matches: &mut Vec<WordMatch>) {
+        let (width, height) = (width(grid) as i32, grid.len() as i32);
+        let mut node = 0;
+        let line = direction.walk((start.0 as i32, start.1 as i32))
+            .take_while(|(x, y)| (0..width).contains(x) && (0..height).contains(y));
+        for (x, y) in line {
+            if !in_bounds(grid, (x, y)) {
+                node = 0;
+                continue;
+            }
+            let char = grid[y as usize][x as usize];
+            while node != 0 && self.nodes[node].child(char).is_none() {
+                node = self.nodes[node].fail;
+            }
+            node = self.nodes[node].child(char).unwrap_or(0);
+            for word in self.nodes[node].outputs.iter() {
+                let (start_x, start_y) = direction.step((x, y), 1 - self.words[*word].len() as i32);
+                matches.push(WordMatch { word: *word, x: start_x as usize, y: start_y as usize, direction });
+            }
+        }
+    }
+}
+
+/// How wide the grid is at its widest row
+fn width(grid: &[Vec<char>]) -> usize {
+    grid.iter().map(|row| row.len()).max().unwrap_or(0)
+}
+
+/**
+ * The cells on the edges of the grid's bounding box that a line in `direction` starts from: the
+ * first column when reading right, the last when reading left, and likewise the top or bottom
+ * row. Diagonals start from both of their edges, with the corner they share only once.
+ */
+fn line_starts(grid: &[Vec<char>], direction: Direction) -> Vec<(usize, usize)> {
+    let (height, width) = (grid.len(), width(grid));
+    if width == 0 || height == 0 {
+        return vec![];
+    }
+    let (x_offset, y_offset) = direction.offset();
+    let column = if x_offset > 0 { 0 } else { width - 1 };
+    let row = if y_offset > 0 { 0 } else { height - 1 };
+    let mut starts = vec![];
+    if x_offset != 0 {
+        starts.extend((0..height).map(|y| (column, y)));
+    }
+    if y_offset != 0 {
+        starts.extend((0..width).filter(|x| x_offset == 0 || *x != column).map(|x| (x, row)));
+    }
+    starts
+}
+
+fn in_bounds(grid: &[Vec<char>], (x, y): (i32, i32)) -> bool {
+    y >= 0 && (y as usize) < grid.len() && x >= 0 && (x as usize) < grid[y as usize].len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::grid;
+
+    #[test]
+    fn test_find_all_positions_and_directions() {
+        let grid = grid(&[
+            "XMAS",
+            "M...",
+            "A...",
+            "S..."
+        ]);
+        let mut matches = WordSearch::new(&["XMAS", "SAM"]).find_all(&grid);
+        matches.sort_by_key(|found| (found.word, found.y, found.x, found.direction));
+        assert_eq!(matches, vec![
+            WordMatch { word: 0, x: 0, y: 0, direction: Direction::Right },
+            WordMatch { word: 0, x: 0, y: 0, direction: Direction::Down },
+            WordMatch { word: 1, x: 3, y: 0, direction: Direction::Left },
+            WordMatch { word: 1, x: 0, y: 3, direction: Direction::Up }
+        ]);
+    }
+
+    #[test]
+    fn test_cells() {
+        let grid = grid(&[
+            "...S",
+            "..A.",
+            ".M..",
+            "X..."
+        ]);
+        let search = WordSearch::new(&["XMAS"]);
+        let found = search.find_all(&grid);
+        assert_eq!(found.len(), 1);
+        assert_eq!(search.cells(&found[0]), vec![(0, 3), (1, 2), (2, 1), (3, 0)]);
+    }
+
+    #[test]
+    fn test_counts_overlapping_words() {
+        let grid = grid(&["SHESHE"]);
+        let search = WordSearch::new(&["HE", "SHE", "HERS", "EH", "S", ""]);
+        // Single letters read the same way in every direction, so they are found once per direction
+        assert_eq!(search.counts(&grid), vec![2, 2, 0, 2, 16, 0]);
+    }
+
+    #[test]
+    fn test_single_word_matches_automaton() {
+        let grid = grid(&["XMASAMX", "MMAXSAS", "AASMXMA", "SMXSAMX"]);
+        // A second word that is never found sends the search through the automaton instead
+        let sorted = |mut matches: Vec<WordMatch>| {
+            matches.sort_by_key(|found| (found.y, found.x, found.direction));
+            matches
+        };
+        let single = sorted(WordSearch::new(&["XMAS"]).find_all(&grid));
+        let automaton = sorted(WordSearch::new(&["XMAS", "QQ"]).find_all(&grid));
+        assert!(!single.is_empty());
+        assert_eq!(single, automaton);
+        assert!(WordSearch::new(&[""]).find_all(&grid).is_empty());
+    }
+
+    #[test]
+    fn test_single_word_matches_automaton_on_ragged_grid() {
+        let sorted = |mut matches: Vec<WordMatch>| {
+            matches.sort_by_key(|found| (found.y, found.x, found.direction));
+            matches
+        };
+        for rows in [&["X", ".X", ".M", ".A", ".S"][..], &["XMASX", "M", "AAA", "SMMMX", "", "SAMX"][..]] {
+            let grid = grid(rows);
+            let single = sorted(WordSearch::new(&["XMAS"]).find_all(&grid));
+            let automaton = sorted(WordSearch::new(&["XMAS", "QQ"]).find_all(&grid));
+            assert!(!single.is_empty());
+            assert_eq!(single, automaton, "{:?}", rows);
+        }
+    }
+
+    #[test]
+    fn test_counts_match_brute_force() {
+        let grid = grid(&[
+            "MMMSXXMASM",
+            "MSAMXMSMSA",
+            "AMXSXMAAMM",
+            "MSAMASMSMX",
+            "XMASAMXAMM",
+            "XXAMMXXAMA",
+            "SMSMSASXSS",
+            "SAXAMASAAA",
+            "MAMMMXMMMM",
+            "MXMXAXMASX"
+        ]);
+        let words = ["XMAS", "MAS", "AM", "MASAM", "XX", "SAMX", "QQ"];
+        let brute_force: Vec<usize> = words.iter().map(|word| {
+            let word: Vec<char> = word.chars().collect();
+            Direction::iter().map(|direction| {
+                (0..grid.len()).flat_map(|y| (0..grid[y].len()).map(move |x| (x, y)))
+                    .filter(|(x, y)| word.iter().enumerate().all(|(index, char)| {
+                        let (x, y) = direction.step((*x as i32, *y as i32), index as i32);
+                        in_bounds(&grid, (x, y)) && grid[y as usize][x as usize] == *char
+                    }))
+                    .count()
+            }).sum()
+        }).collect();
+        assert_eq!(WordSearch::new(&words).counts(&grid), brute_force);
+    }
+}
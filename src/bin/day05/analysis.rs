@@ -0,0 +1,145 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter, Result};
+use itertools::Itertools;
+use crate::rule_graph::RuleGraph;
+
+/// Every page reachable from each page by following one or more rules
+pub fn transitive_closure(graph: &RuleGraph) -> HashMap<usize, HashSet<usize>> {
+    graph.pages().into_iter()
+        .map(|page| {
+            let mut reached: HashSet<usize> = HashSet::new();
+            let mut stack: Vec<usize> = graph.successors(page).collect();
+            while let Some(next) = stack.pop() {
+                if reached.insert(next) {
+                    stack.extend(graph.successors(next));
+                }
+            }
+            (page, reached)
+        })
+        .collect()
+}
+
+/**
+ * What the rules say as a whole. `redundant` are the rules `X|Y` that already follow from a
+ * chain of other rules through some page between `X` and `Y`, `unconstrained` are the pairs of
+ * pages the rules never order, and `cycle` is a loop in the rules if there is one, in which case
+ * no order of every page can satisfy them.
+ *
+ * Around a cycle every rule follows from the others, but they can't all be dropped, so rules
+ * are only reported as redundant when there is no cycle.
+ */
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct RuleAnalysis {
+    pub pages: usize,
+    pub rules: usize,
+    pub redundant: Vec<(usize, usize)>,
+    pub unconstrained: Vec<(usize, usize)>,
+    pub cycle: Option<Vec<usize>>
+}
+
+impl RuleAnalysis {
+    pub fn new(graph: &RuleGraph) -> Self {
+        let closure = transitive_closure(graph);
+        let pages = graph.pages();
+        let rules = graph.rules();
+        let cycle = pages.iter()
+            .find(|page| closure[page].contains(page))
+            .map(|page| shortest_cycle(graph, *page));
+        let redundant = match cycle {
+            Some(_) => vec![],
+            None => rules.iter().copied()
+                .filter(|(before, after)| {
+                    graph.successors(*before).any(|between| between != *after && closure[&between].contains(after))
+                })
+                .collect()
+        };
+        let unconstrained = pages.iter().copied()
+            .tuple_combinations()
+            .filter(|(first, second)| !closure[first].contains(second) && !closure[second].contains(first))
+            .collect();
+        Self { pages: pages.len(), rules: rules.len(), redundant, unconstrained, cycle }
+    }
+}
+
+impl Display for RuleAnalysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let pairs = |pairs: &[(usize, usize)], separator: &str| {
+            pairs.iter().map(|(first, second)| format!("{}{}{}", first, separator, second)).join(", ")
+        };
+        writeln!(f, "{} pages, {} rules", self.pages, self.rules)?;
+        writeln!(f, "Redundant rules ({}): {}", self.redundant.len(), pairs(&self.redundant, "|"))?;
+        writeln!(f, "Unconstrained pairs ({}): {}", self.unconstrained.len(), pairs(&self.unconstrained, " "))?;
+        match &self.cycle {
+            Some(cycle) => writeln!(f, "Cyclic: {} -> {}", cycle.iter().join(" -> "), cycle[0]),
+            None => writeln!(f, "Acyclic")
+        }
+    }
+}
+
+/// The rules as a Graphviz digraph, with redundant rules dashed
+pub fn to_dot(graph: &RuleGraph, analysis: &RuleAnalysis) -> String {
+    let mut dot = String::from("digraph rules {\n");
+    for (before, after) in graph.rules() {
+        let style = if analysis.redundant.contains(&(before, after)) { " [style=dashed]" } else { "" };
+        dot.push_str(&format!("    {} -> {}{};\n", before, after, style));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// The shortest loop of rules from `page` back to itself, found breadth first
+fn shortest_cycle(graph: &RuleGraph, page: usize) -> Vec<usize> {
+    let mut parents: HashMap<usize, usize> = HashMap::new();
+    let mut queue: VecDeque<usize> = VecDeque::from([page]);
+    while let Some(current) = queue.pop_front() {
+        for next in graph.successors(current).sorted() {
+            if next == page {
+                let mut cycle = vec![current];
+                while *cycle.last().unwrap() != page {
+                    cycle.push(parents[cycle.last().unwrap()]);
+                }
+                cycle.reverse();
+                return cycle;
+            }
+            if let std::collections::hash_map::Entry::Vacant(entry) = parents.entry(next) {
+                entry.insert(current);
+                queue.push_back(next);
+            }
+        }
+    }
+    panic!("{} is not on a cycle", page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transitive_closure() {
+        let closure = transitive_closure(&RuleGraph::from_pairs(&[(1, 2), (2, 3), (4, 3)]));
+        assert_eq!(closure[&1], HashSet::from([2, 3]));
+        assert_eq!(closure[&3], HashSet::new());
+        assert_eq!(closure[&4], HashSet::from([3]));
+    }
+
+    #[test]
+    fn test_analysis() {
+        let graph = RuleGraph::from_pairs(&[(1, 2), (2, 3), (1, 3), (4, 3)]);
+        let analysis = RuleAnalysis::new(&graph);
+        assert_eq!(analysis.redundant, vec![(1, 3)]);
+        assert_eq!(analysis.unconstrained, vec![(1, 4), (2, 4)]);
+        assert_eq!(analysis.cycle, None);
+        assert_eq!(
+            to_dot(&graph, &analysis),
+            "digraph rules {\n    1 -> 2;\n    1 -> 3 [style=dashed];\n    2 -> 3;\n    4 -> 3;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_analysis_cycle() {
+        let analysis = RuleAnalysis::new(&RuleGraph::from_pairs(&[(5, 1), (3, 1), (1, 2), (2, 3), (2, 4)]));
+        assert_eq!(analysis.cycle, Some(vec![1, 2, 3]));
+        assert_eq!(analysis.redundant, vec![]);
+        assert!(analysis.to_string().ends_with("Cyclic: 1 -> 2 -> 3 -> 1\n"));
+    }
+}
@@ -1,87 +1,116 @@
-use std::{cmp::Ordering, collections::{HashMap, HashSet}};
+use analysis::RuleAnalysis;
+use parser::Input;
+use itertools::Itertools;
+use print_queue::{Classification, PrintQueue};
+use rule_graph::{OrderError, RuleGraph};
+use rule_index::RuleIndex;
+use violation::ViolationReport;
+
+mod analysis;
+mod parser;
+mod print_queue;
+mod rule_graph;
+mod rule_index;
+mod violation;
 
 fn main() {
-    let (rules, updates) = parse_input("./src/bin/day05/input.txt");
-    let (valid, invalid) = split_valid_and_invalid_updates(&rules, &updates);
+    let Input { rules, updates, even_updates } = parse_input("./src/bin/day05/input.txt");
+    even_updates.iter().for_each(|line| {
+        eprintln!("Warning: the update on line {} has an even number of pages, so its middle page is ambiguous", line);
+    });
+    // `--edit` starts a print queue from the input and applies commands from stdin to it
+    if std::env::args().any(|argument| argument == "--edit") {
+        edit(PrintQueue::new(&rules, &updates));
+        return;
+    }
+    let graph = RuleGraph::from(&rules);
+    let (valid, invalid) = split_valid_and_invalid_updates(&RuleIndex::from(&graph), &updates);
+    // `--explain` lists the rules each invalid update breaks. `--analyze` reports on the rules as
+    // a whole, and `--dot` prints them as a Graphviz graph
+    let flags: Vec<String> = std::env::args().skip(1).collect();
+    if flags.iter().any(|flag| flag == "--explain") {
+        invalid.iter().for_each(|update| print!("{}", ViolationReport::new(&graph, update)));
+    }
+    if flags.iter().any(|flag| flag == "--analyze" || flag == "--dot") {
+        let analysis = RuleAnalysis::new(&graph);
+        if flags.iter().any(|flag| flag == "--analyze") {
+            print!("{}", analysis);
+        }
+        if flags.iter().any(|flag| flag == "--dot") {
+            print!("{}", analysis::to_dot(&graph, &analysis));
+        }
+    }
     // Part 1
     let middle_valid_sum = sum_of_middle_updates(&valid);
     println!("Middle sum: {}", middle_valid_sum);
     // Part 2
-    let middle_corrected_invalid_sum = sum_of_middle_updates(&valid_sort(&rules, &invalid));
+    let corrected = valid_sort(&graph, &invalid).unwrap_or_else(|error| panic!("{}", error));
+    let middle_corrected_invalid_sum = sum_of_middle_updates(&corrected);
     println!("Corrected invalid middle sum: {}", middle_corrected_invalid_sum);
 }
 
-fn parse_input(file_path: &str) -> (HashMap<usize, Vec<usize>>, Vec<Vec<usize>>) {
-    // TODO come back to this and try with parser combinators
-    let sections: Vec<String> = std::fs::read_to_string(file_path)
-        .expect(&format!("Error reading from file path: {}", file_path))
-        .split("\n\n")
-        .map(|str| str.to_string())
-        .collect();
-    let mut rules: HashMap<usize, Vec<usize>> = HashMap::new();
-    sections[0].split("\n")
-        .for_each(|line| {
-            let rule = line.split("|")
-            .map(|num| num.parse::<usize>().expect(&format!("Invalid input. {} is not a number.", num)))
-            .collect::<Vec<usize>>();
-            match rules.get_mut(&rule[0]) {
-                Some(precedent) => precedent.push(rule[1]),
-                None => { rules.insert(rule[0], vec![rule[1]]); }
-            };
-        });
-    let updates = sections[1].split("\n")
-        .map(|line| {
-            line.split(",")
-            .map(|num| num.parse::<usize>().expect(&format!("Invalid input. {} is not a number.", num)))
-            .collect::<Vec<usize>>()
-        })
-        .collect();
-
-    (rules, updates)
+/**
+ * Applies `add rule X|Y`, `remove rule X|Y`, `add update A,B,...` and `remove update <id>`
+ * commands one line at a time, printing the totals after each.
+ */
+fn edit(mut queue: PrintQueue) {
+    let number = |text: &str| text.trim().parse::<usize>().map_err(|_| format!("{} is not a number.", text));
+    let rule = |text: &str| -> Result<(usize, usize), String> {
+        let (before, after) = text.split_once('|').ok_or_else(|| format!("{} is not a rule of the form X|Y.", text))?;
+        Ok((number(before)?, number(after)?))
+    };
+    for line in std::io::stdin().lines() {
+        let line = line.unwrap_or_else(|error| panic!("Error reading commands: {}", error));
+        let result = match line.trim().split_once(' ') {
+            Some(("add", rest)) if rest.starts_with("rule ") => rule(&rest[5..]).map(|(before, after)| {
+                queue.add_rule(before, after);
+            }),
+            Some(("remove", rest)) if rest.starts_with("rule ") => rule(&rest[5..]).map(|(before, after)| {
+                queue.remove_rule(before, after);
+            }),
+            Some(("add", rest)) if rest.starts_with("update ") => {
+                rest[7..].split(',').map(number).collect::<Result<Vec<usize>, String>>()
+                    .map(|pages| {
+                        let id = queue.add_update(pages);
+                        let classification = match queue.classification(id).unwrap() {
+                            Classification::Valid => "valid".to_string(),
+                            Classification::Corrected(order) => format!("invalid, corrected to {}", order.iter().join(",")),
+                            Classification::Unordered(error) => format!("invalid, {}", error)
+                        };
+                        println!("Added update {}: {}", id, classification);
+                    })
+            }
+            Some(("remove", rest)) if rest.starts_with("update ") => number(&rest[7..]).map(|id| {
+                if queue.remove_update(id).is_none() {
+                    eprintln!("There is no update {}", id);
+                }
+            }),
+            _ => Err(format!("{} is not a command.", line.trim()))
+        };
+        match result {
+            Ok(()) => match queue.corrected_total() {
+                Ok(corrected) => println!("Middle sum: {}, corrected invalid middle sum: {}", queue.valid_total(), corrected),
+                Err(error) => println!("Middle sum: {}, corrected invalid middle sum unknown: {}", queue.valid_total(), error)
+            },
+            Err(message) => eprintln!("Invalid command. {}", message)
+        }
+    }
+}
+
+fn parse_input(file_path: &str) -> Input {
+    let text = std::fs::read_to_string(file_path)
+        .unwrap_or_else(|_| panic!("Error reading from file path: {}", file_path));
+    parser::parse(&text).unwrap_or_else(|error| panic!("{}", error))
 }
 
-fn split_valid_and_invalid_updates(rules: &HashMap<usize, Vec<usize>>, updates: &Vec<Vec<usize>>) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+fn split_valid_and_invalid_updates(index: &RuleIndex, updates: &[Vec<usize>]) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
     updates.iter()
-        .fold((vec![], vec![]), |(mut valid, mut invalid), update| {
-            let mut visited: HashSet<usize> = HashSet::new();
-            let is_valid = !update.iter().any(|page_num| {
-                match rules.get(page_num) {
-                    Some(value) => {
-                        if value.iter().any(|page| visited.contains(page)) {
-                            return true;
-                        }
-                    }
-                    _ => {}
-                }
-                visited.insert(*page_num);
-                false
-            });
-            if is_valid {
-                valid.push(update.clone());
-            } else {
-                invalid.push(update.clone());
-            }
-            (valid, invalid)
-        })
+        .cloned()
+        .partition(|update| index.is_ordered(update))
 }
 
-fn valid_sort(rules: &HashMap<usize, Vec<usize>>, updates: &Vec<Vec<usize>>) -> Vec<Vec<usize>> {
-    updates.iter().map(|update| {
-        let mut new_update = update.clone();
-        new_update.sort_by(|a, b| {
-            match rules.get(a) {
-                Some(value) => {
-                    if value.contains(b) {
-                        Ordering::Less
-                    } else {
-                        Ordering::Equal
-                    }
-                }
-                _ => Ordering::Equal
-            }
-        });
-        new_update
-    }).collect()
+fn valid_sort(graph: &RuleGraph, updates: &[Vec<usize>]) -> Result<Vec<Vec<usize>>, OrderError> {
+    updates.iter().map(|update| graph.order(update)).collect()
 }
 
 fn sum_of_middle_updates(updates: &Vec<Vec<usize>>) -> usize {
@@ -92,7 +121,9 @@ fn sum_of_middle_updates(updates: &Vec<Vec<usize>>) -> usize {
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashMap;
     use super::*;
+    use violation::BrokenRule;
 
     #[test]
     fn test_parse_input() {            
@@ -113,7 +144,7 @@ mod test {
             vec![97,13,75,29,47]
         ];
 
-        assert_eq!(parse_input("./src/bin/day05/sample_input.txt"), (rules, updates));
+        assert_eq!(parse_input("./src/bin/day05/sample_input.txt"), Input { rules, updates, even_updates: vec![] });
     }
 
     #[test]
@@ -145,7 +176,7 @@ mod test {
             vec![97,13,75,29,47]
         ];
 
-        assert_eq!(split_valid_and_invalid_updates(&rules, &updates), (valid_updates, invalid_updates));
+        assert_eq!(split_valid_and_invalid_updates(&RuleIndex::from(&RuleGraph::from(&rules)), &updates), (valid_updates, invalid_updates));
     }
 
     #[test]
@@ -169,7 +200,10 @@ mod test {
             vec![97, 75, 47, 29, 13]
         ];
 
-        assert_eq!(valid_sort(&rules, &invalid_updates), sorted_updates)
+        assert_eq!(valid_sort(&RuleGraph::from(&rules), &invalid_updates), Ok(sorted_updates));
+        // A repeated page stays, so the middle page is taken from an update of the same length
+        let repeated = valid_sort(&RuleGraph::from(&rules), &[vec![61, 13, 61]]).unwrap();
+        assert_eq!(sum_of_middle_updates(&repeated), 61);
     }
 
     #[test]
@@ -188,4 +222,28 @@ mod test {
         assert_eq!(sum_of_middle_updates(&valid_updates), 143);
         assert_eq!(sum_of_middle_updates(&corrected_invalid_updates), 123);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_violation_report() {
+        let Input { rules, updates, .. } = parse_input("./src/bin/day05/sample_input.txt");
+        let graph = RuleGraph::from(&rules);
+        let reports: Vec<ViolationReport> = updates.iter().map(|update| ViolationReport::new(&graph, update)).collect();
+        assert!(reports[..3].iter().all(|report| report.broken.is_empty() && report.moves == Some(0)));
+
+        assert_eq!(reports[3].broken, vec![BrokenRule { before: 97, after: 75, before_index: 1, after_index: 0 }]);
+        assert_eq!(reports[3].moves, Some(1));
+        assert_eq!(reports[4].to_string(), "61,13,29: 1 broken rules, 1 moves to fix\n  29|13 (13 at index 1 is before 29 at index 2)\n");
+
+        let broken: Vec<(usize, usize)> = reports[5].broken.iter().map(|rule| (rule.before, rule.after)).collect();
+        assert_eq!(broken, vec![(75, 13), (29, 13), (47, 13), (47, 29)]);
+        assert_eq!(reports[5].inversions(), 4);
+        assert_eq!(reports[5].moves, Some(2));
+    }
+
+    #[test]
+    fn test_print_queue_totals() {
+        let Input { rules, updates, .. } = parse_input("./src/bin/day05/sample_input.txt");
+        let queue = PrintQueue::new(&rules, &updates);
+        assert_eq!((queue.valid_total(), queue.corrected_total()), (143, Ok(123)));
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result};
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "Invalid input on line {}. {}", self.line, self.message)
+    }
+}
+
+/**
+ * The ordering rules, keyed by the page that has to come first, and the updates. `even_updates`
+ * are the line numbers of updates with an even number of pages, which have no single middle page.
+ */
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Input {
+    pub rules: HashMap<usize, Vec<usize>>,
+    pub updates: Vec<Vec<usize>>,
+    pub even_updates: Vec<usize>
+}
+
+/**
+ * Parses rules of the form `X|Y` followed by updates of comma separated pages. A line with a `|`
+ * is a rule and any other is an update, so blank lines can go anywhere and there needn't be any
+ * rules at all. Lines may end in `\r\n` or trailing whitespace and anything after a `#` is a
+ * comment.
+ */
+pub fn parse(text: &str) -> std::result::Result<Input, ParseError> {
+    let mut input = Input { rules: HashMap::new(), updates: vec![], even_updates: vec![] };
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: String| ParseError { line: line_number, message };
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some((before, after)) = line.split_once('|') {
+            if !input.updates.is_empty() {
+                return Err(error(format!("{} is a rule, but rules have to come before the updates.", line)));
+            }
+            let before = page(before).map_err(error)?;
+            let after = page(after).map_err(error)?;
+            input.rules.entry(before).or_default().push(after);
+        } else {
+            let update = line.split(',').map(page).collect::<std::result::Result<Vec<usize>, String>>().map_err(error)?;
+            if update.len() % 2 == 0 {
+                input.even_updates.push(line_number);
+            }
+            input.updates.push(update);
+        }
+    }
+    Ok(input)
+}
+
+fn page(text: &str) -> std::result::Result<usize, String> {
+    let text = text.trim();
+    text.parse::<usize>().map_err(|_| format!("{:?} is not a page number.", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tolerates_formatting() {
+        let text = "# rules\r\n\r\n1|2  \r\n 2 | 3 # trailing comment\r\n\r\n\r\n3,1,2\r\n\r\n1,2\t\r\n";
+        let input = parse(text).unwrap();
+        assert_eq!(input.rules, HashMap::from([(1, vec![2]), (2, vec![3])]));
+        assert_eq!(input.updates, vec![vec![3, 1, 2], vec![1, 2]]);
+        assert_eq!(input.even_updates, vec![9]);
+    }
+
+    #[test]
+    fn test_parse_blank_lines_between_rules() {
+        let input = parse("1|2\n\n3|4\n\n1,2,3").unwrap();
+        assert_eq!(input.rules, HashMap::from([(1, vec![2]), (3, vec![4])]));
+        assert_eq!(input.updates, vec![vec![1, 2, 3]]);
+    }
+
+    #[test]
+    fn test_parse_without_rules() {
+        let input = parse("\n1,2,3\n7\n").unwrap();
+        assert!(input.rules.is_empty());
+        assert_eq!(input.updates, vec![vec![1, 2, 3], vec![7]]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |text: &str| parse(text).unwrap_err().to_string();
+        assert_eq!(error("1|2\n3-4\n"), "Invalid input on line 2. \"3-4\" is not a page number.");
+        assert_eq!(error("1|2|3\n"), "Invalid input on line 1. \"2|3\" is not a page number.");
+        assert_eq!(error("1|2\n\n1,x,3\n"), "Invalid input on line 3. \"x\" is not a page number.");
+        assert_eq!(error("1|2\n\n1,,3\n"), "Invalid input on line 3. \"\" is not a page number.");
+        assert_eq!(error("1|2\n\n1,2,3\n4|5\n"), "Invalid input on line 4. 4|5 is a rule, but rules have to come before the updates.");
+    }
+}
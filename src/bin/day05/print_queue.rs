@@ -0,0 +1,254 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use crate::rule_graph::{OrderError, RuleGraph};
+
+pub type UpdateId = usize;
+
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Classification {
+    Valid,
+    /// Invalid, and this is the order the rules put it in
+    Corrected(Vec<usize>),
+    /// Invalid, and the rules don't give it a single order
+    Unordered(OrderError)
+}
+
+struct Update {
+    pages: Vec<usize>,
+    classification: Classification
+}
+
+/**
+ * Rules and updates that change over time. Each update is classified when it is added, and
+ * adding or removing a rule only re-classifies the updates that contain both of its pages, so
+ * the Part 1 and Part 2 totals are kept up to date without going over every update again.
+ */
+#[derive(Default)]
+pub struct PrintQueue {
+    graph: RuleGraph,
+    updates: BTreeMap<UpdateId, Update>,
+    next_id: UpdateId,
+    // Which updates each page appears in
+    containing: HashMap<usize, HashSet<UpdateId>>,
+    // The updates the rules don't give a single order, which leave Part 2 without a total
+    unordered: BTreeSet<UpdateId>,
+    valid_total: usize,
+    corrected_total: usize
+}
+
+impl PrintQueue {
+    pub fn new(rules: &HashMap<usize, Vec<usize>>, updates: &[Vec<usize>]) -> Self {
+        let mut queue = Self { graph: RuleGraph::from(rules), ..Self::default() };
+        updates.iter().for_each(|update| { queue.add_update(update.clone()); });
+        queue
+    }
+
+    /// Adds the rule `before|after`, returning whether it is new
+    pub fn add_rule(&mut self, before: usize, after: usize) -> bool {
+        let added = self.graph.add_rule(before, after);
+        if added {
+            self.reclassify_containing(before, after);
+        }
+        added
+    }
+
+    /// Removes the rule `before|after`, returning whether there was one
+    pub fn remove_rule(&mut self, before: usize, after: usize) -> bool {
+        let removed = self.graph.remove_rule(before, after);
+        if removed {
+            self.reclassify_containing(before, after);
+        }
+        removed
+    }
+
+    pub fn add_update(&mut self, pages: Vec<usize>) -> UpdateId {
+        let id = self.next_id;
+        self.next_id += 1;
+        pages.iter().for_each(|page| { self.containing.entry(*page).or_default().insert(id); });
+        let classification = self.classify(&pages);
+        let update = Update { pages, classification };
+        self.count(id, &update, true);
+        self.updates.insert(id, update);
+        id
+    }
+
+    /// Removes an update, returning its pages if there was one with that id
+    pub fn remove_update(&mut self, id: UpdateId) -> Option<Vec<usize>> {
+        let update = self.updates.remove(&id)?;
+        self.count(id, &update, false);
+        update.pages.iter().for_each(|page| {
+            if let Some(ids) = self.containing.get_mut(page) {
+                ids.remove(&id);
+            }
+        });
+        Some(update.pages)
+    }
+
+    pub fn classification(&self, id: UpdateId) -> Option<&Classification> {
+        self.updates.get(&id).map(|update| &update.classification)
+    }
+
+    /// The sum of the middle pages of the valid updates
+    pub fn valid_total(&self) -> usize {
+        self.valid_total
+    }
+
+    /// The sum of the middle pages of the invalid updates once corrected, unless one can't be
+    pub fn corrected_total(&self) -> Result<usize, OrderError> {
+        match self.unordered.first().map(|id| &self.updates[id].classification) {
+            Some(Classification::Unordered(error)) => Err(error.clone()),
+            _ => Ok(self.corrected_total)
+        }
+    }
+
+    fn classify(&self, pages: &[usize]) -> Classification {
+        let ordered = (0..pages.len()).all(|first| {
+            (first + 1..pages.len()).all(|second| !self.graph.has_rule(pages[second], pages[first]))
+        });
+        if ordered {
+            return Classification::Valid;
+        }
+        match self.graph.order(pages) {
+            Ok(order) => Classification::Corrected(order),
+            Err(error) => Classification::Unordered(error)
+        }
+    }
+
+    /**
+     * Adds an update's middle page to the totals, or takes it back off when `add` isn't set. An
+     * empty update has no middle page and counts for nothing.
+     */
+    fn count(&mut self, id: UpdateId, update: &Update, add: bool) {
+        let (total, pages) = match &update.classification {
+            Classification::Valid => (&mut self.valid_total, &update.pages),
+            Classification::Corrected(order) => (&mut self.corrected_total, order),
+            Classification::Unordered(_) => {
+                if add {
+                    self.unordered.insert(id);
+                } else {
+                    self.unordered.remove(&id);
+                }
+                return;
+            }
+        };
+        let Some(middle) = pages.get(pages.len() / 2) else { return };
+        if add {
+            *total += middle;
+        } else {
+            *total -= middle;
+        }
+    }
+
+    fn reclassify_containing(&mut self, first: usize, second: usize) {
+        let (Some(with_first), Some(with_second)) = (self.containing.get(&first), self.containing.get(&second)) else {
+            return;
+        };
+        let affected: Vec<UpdateId> = with_first.intersection(with_second).copied().collect();
+        for id in affected {
+            let mut update = self.updates.remove(&id).unwrap();
+            self.count(id, &update, false);
+            update.classification = self.classify(&update.pages);
+            self.count(id, &update, true);
+            self.updates.insert(id, update);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rule_index::RuleIndex;
+
+    #[test]
+    fn test_incremental_rules() {
+        let mut queue = PrintQueue::new(&HashMap::new(), &[vec![3, 1, 2], vec![4, 5, 6]]);
+        assert_eq!(queue.valid_total(), 1 + 5);
+        assert_eq!(queue.corrected_total(), Ok(0));
+
+        assert!(queue.add_rule(1, 3));
+        assert!(!queue.add_rule(1, 3));
+        assert_eq!(queue.valid_total(), 5);
+        // 1 before 3 leaves 2 free, so update 0 has no single order
+        assert_eq!(queue.corrected_total(), Err(OrderError::Ambiguous(vec![1, 2])));
+
+        queue.add_rule(2, 3);
+        queue.add_rule(1, 2);
+        assert_eq!(queue.classification(0), Some(&Classification::Corrected(vec![1, 2, 3])));
+        assert_eq!(queue.corrected_total(), Ok(2));
+
+        assert!(queue.remove_rule(1, 3));
+        assert!(!queue.remove_rule(1, 3));
+        assert_eq!(queue.classification(0), Some(&Classification::Corrected(vec![1, 2, 3])));
+        queue.remove_rule(2, 3);
+        assert_eq!(queue.classification(0), Some(&Classification::Valid));
+        assert_eq!((queue.valid_total(), queue.corrected_total()), (1 + 5, Ok(0)));
+    }
+
+    #[test]
+    fn test_incremental_updates() {
+        let rules = HashMap::from([(1, vec![2, 3]), (2, vec![3])]);
+        let mut queue = PrintQueue::new(&rules, &[]);
+        let valid = queue.add_update(vec![1, 2, 3]);
+        let invalid = queue.add_update(vec![3, 2, 1]);
+        assert_eq!((queue.valid_total(), queue.corrected_total()), (2, Ok(2)));
+        assert_eq!(queue.remove_update(invalid), Some(vec![3, 2, 1]));
+        assert_eq!(queue.remove_update(invalid), None);
+        assert_eq!((queue.valid_total(), queue.corrected_total()), (2, Ok(0)));
+        queue.remove_update(valid);
+        assert_eq!(queue.valid_total(), 0);
+    }
+
+    #[test]
+    fn test_empty_update() {
+        let mut queue = PrintQueue::new(&HashMap::new(), &[vec![4, 5, 6]]);
+        let empty = queue.add_update(vec![]);
+        assert_eq!(queue.classification(empty), Some(&Classification::Valid));
+        assert_eq!((queue.valid_total(), queue.corrected_total()), (5, Ok(0)));
+        assert_eq!(queue.remove_update(empty), Some(vec![]));
+        assert_eq!(queue.valid_total(), 5);
+    }
+
+    #[test]
+    fn test_unordered_updates_come_and_go() {
+        let mut queue = PrintQueue::new(&HashMap::from([(1, vec![3])]), &[vec![3, 1, 2]]);
+        let cycle = queue.add_update(vec![5, 6]);
+        assert_eq!(queue.corrected_total(), Err(OrderError::Ambiguous(vec![1, 2])));
+        queue.add_rule(1, 2);
+        queue.add_rule(2, 3);
+        assert_eq!(queue.corrected_total(), Ok(2));
+        queue.add_rule(5, 6);
+        queue.add_rule(6, 5);
+        assert_eq!(queue.corrected_total(), Err(OrderError::Cycle(vec![5, 6])));
+        queue.remove_update(cycle);
+        assert_eq!(queue.corrected_total(), Ok(2));
+    }
+
+    #[test]
+    fn test_validity_matches_rule_index() {
+        // The queue checks validity pair by pair, which has to agree with the bitsets used for Part 1
+        let mut seed = 46usize;
+        let mut random = |below: usize| {
+            seed = (seed * 1_103_515_245 + 12_345) % (1 << 31);
+            (seed >> 8) % below
+        };
+        let mut rules: Vec<(usize, usize)> = (0..40).map(|_| (random(12), random(12))).collect();
+        let updates: Vec<Vec<usize>> = (0..60).map(|_| (0..random(7)).map(|_| random(12)).collect()).collect();
+        let mut map: HashMap<usize, Vec<usize>> = HashMap::new();
+        rules.iter().for_each(|(before, after)| map.entry(*before).or_default().push(*after));
+        let mut queue = PrintQueue::new(&map, &updates);
+        for _ in 0..20 {
+            let index = RuleIndex::from(&RuleGraph::from_pairs(&rules));
+            for (id, update) in updates.iter().enumerate() {
+                let valid = queue.classification(id) == Some(&Classification::Valid);
+                assert_eq!(valid, index.is_ordered(update), "{:?} with rules {:?}", update, rules);
+            }
+            let rule = (random(12), random(12));
+            if rules.contains(&rule) {
+                rules.retain(|existing| *existing != rule);
+                assert!(queue.remove_rule(rule.0, rule.1));
+            } else {
+                rules.push(rule);
+                assert!(queue.add_rule(rule.0, rule.1));
+            }
+        }
+    }
+}
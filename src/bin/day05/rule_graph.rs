@@ -0,0 +1,198 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter, Result};
+use itertools::Itertools;
+
+/// Why the pages of an update couldn't be put in order
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum OrderError {
+    /// The rules between these pages go round in a loop, each page having to come before the next
+    Cycle(Vec<usize>),
+    /// Nothing in the rules decides which of these pages comes first
+    Ambiguous(Vec<usize>)
+}
+
+impl Display for OrderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            OrderError::Cycle(pages) => {
+                write!(f, "Rules form a cycle: {} -> {}", pages.iter().join(" -> "), pages[0])
+            }
+            OrderError::Ambiguous(pages) => {
+                write!(f, "Rules don't decide which of pages {} comes first", pages.iter().join(", "))
+            }
+        }
+    }
+}
+
+/// The ordering rules as a directed graph, with an edge from `X` to `Y` for every rule `X|Y`
+#[derive(Default)]
+pub struct RuleGraph {
+    successors: HashMap<usize, HashSet<usize>>
+}
+
+impl RuleGraph {
+    /// Every page named by some rule, in ascending order
+    pub fn pages(&self) -> Vec<usize> {
+        self.successors.iter()
+            .flat_map(|(before, afters)| std::iter::once(*before).chain(afters.iter().copied()))
+            .unique()
+            .sorted()
+            .collect()
+    }
+
+    /// Every rule as `(before, after)`, in ascending order
+    pub fn rules(&self) -> Vec<(usize, usize)> {
+        self.successors.iter()
+            .flat_map(|(before, afters)| afters.iter().map(move |after| (*before, *after)))
+            .sorted()
+            .collect()
+    }
+
+    /// The pages that have to be printed after `page`
+    pub fn successors(&self, page: usize) -> impl Iterator<Item = usize> + '_ {
+        self.successors.get(&page).into_iter().flatten().copied()
+    }
+
+    /// Whether there is a rule that `before` has to be printed before `after`
+    pub fn has_rule(&self, before: usize, after: usize) -> bool {
+        self.successors.get(&before).is_some_and(|successors| successors.contains(&after))
+    }
+
+    /// Adds the rule `before|after`, returning whether it is new
+    pub fn add_rule(&mut self, before: usize, after: usize) -> bool {
+        self.successors.entry(before).or_default().insert(after)
+    }
+
+    /// Removes the rule `before|after`, returning whether there was one
+    pub fn remove_rule(&mut self, before: usize, after: usize) -> bool {
+        let Some(successors) = self.successors.get_mut(&before) else { return false };
+        let removed = successors.remove(&after);
+        if successors.is_empty() {
+            self.successors.remove(&before);
+        }
+        removed
+    }
+
+    /**
+     * Orders the pages of an update by the rules between them, with Kahn's algorithm. Rules
+     * about pages that aren't in the update are ignored. The order has to be the only one the
+     * rules allow, so it fails if at any point more than one page could come next, or if no page
+     * can because the rules are cyclic. A page that appears more than once is kept as many
+     * times, side by side.
+     */
+    pub fn order(&self, update: &[usize]) -> std::result::Result<Vec<usize>, OrderError> {
+        let mut copies: HashMap<usize, usize> = HashMap::new();
+        update.iter().for_each(|page| *copies.entry(*page).or_default() += 1);
+        let pages: HashSet<usize> = copies.keys().copied().collect();
+        let successors = |page: usize| self.successors(page).filter(|successor| pages.contains(successor));
+        let mut in_degrees: HashMap<usize, usize> = pages.iter().map(|page| (*page, 0)).collect();
+        pages.iter().flat_map(|page| successors(*page)).for_each(|successor| {
+            *in_degrees.get_mut(&successor).unwrap() += 1;
+        });
+
+        let mut order = Vec::with_capacity(update.len());
+        let mut ready: Vec<usize> = update.iter().copied().unique().filter(|page| in_degrees[page] == 0).collect();
+        while order.len() < update.len() {
+            match ready.len() {
+                0 => return Err(OrderError::Cycle(self.find_cycle(&in_degrees))),
+                1 => {}
+                _ => {
+                    ready.sort_by_key(|page| update.iter().position(|other| other == page));
+                    return Err(OrderError::Ambiguous(ready));
+                }
+            }
+            let page = ready.pop().unwrap();
+            order.extend(std::iter::repeat_n(page, copies[&page]));
+            for successor in successors(page) {
+                let in_degree = in_degrees.get_mut(&successor).unwrap();
+                *in_degree -= 1;
+                if *in_degree == 0 {
+                    ready.push(successor);
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /**
+     * Finds a cycle among the pages that still have rules in front of them. Each of them has a
+     * predecessor that is also left, so walking back through predecessors has to repeat a page.
+     */
+    fn find_cycle(&self, in_degrees: &HashMap<usize, usize>) -> Vec<usize> {
+        let remaining: Vec<usize> = in_degrees.iter()
+            .filter(|(_, in_degree)| **in_degree > 0)
+            .map(|(page, _)| *page)
+            .sorted()
+            .collect();
+        let mut path = vec![remaining[0]];
+        loop {
+            let page = *path.last().unwrap();
+            let predecessor = remaining.iter().copied().find(|predecessor| self.has_rule(*predecessor, page)).unwrap();
+            if let Some(start) = path.iter().position(|visited| *visited == predecessor) {
+                let mut cycle = path.split_off(start);
+                cycle.reverse();
+                // Starting from the lowest page, so the same cycle is always reported the same way
+                let lowest = cycle.iter().position_min().unwrap();
+                cycle.rotate_left(lowest);
+                return cycle;
+            }
+            path.push(predecessor);
+        }
+    }
+}
+
+#[cfg(test)]
+impl RuleGraph {
+    /// A graph with a rule for each `(before, after)` pair
+    pub fn from_pairs(rules: &[(usize, usize)]) -> Self {
+        let mut graph = Self::default();
+        for (before, after) in rules {
+            graph.add_rule(*before, *after);
+        }
+        graph
+    }
+}
+
+impl From<&HashMap<usize, Vec<usize>>> for RuleGraph {
+    fn from(rules: &HashMap<usize, Vec<usize>>) -> Self {
+        let successors = rules.iter()
+            .map(|(before, afters)| (*before, afters.iter().copied().collect()))
+            .collect();
+        Self { successors }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order_ignores_rules_outside_the_update() {
+        let graph = RuleGraph::from_pairs(&[(1, 2), (2, 3), (3, 4), (4, 1)]);
+        assert_eq!(graph.order(&[3, 1, 2]), Ok(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn test_order_cycle() {
+        let graph = RuleGraph::from_pairs(&[(1, 2), (2, 3), (3, 1), (5, 1)]);
+        let error = graph.order(&[5, 3, 2, 1]).unwrap_err();
+        assert_eq!(error, OrderError::Cycle(vec![1, 2, 3]));
+        assert_eq!(error.to_string(), "Rules form a cycle: 1 -> 2 -> 3 -> 1");
+    }
+
+    #[test]
+    fn test_order_ambiguous() {
+        let graph = RuleGraph::from_pairs(&[(1, 2), (1, 3)]);
+        let error = graph.order(&[3, 2, 1]).unwrap_err();
+        assert_eq!(error, OrderError::Ambiguous(vec![3, 2]));
+        assert_eq!(error.to_string(), "Rules don't decide which of pages 3, 2 comes first");
+        assert_eq!(graph.order(&[3, 1]), Ok(vec![1, 3]));
+    }
+
+    #[test]
+    fn test_order_keeps_repeated_pages() {
+        let graph = RuleGraph::from_pairs(&[(1, 2), (2, 3)]);
+        assert_eq!(graph.order(&[2, 1, 2]), Ok(vec![1, 2, 2]));
+        assert_eq!(graph.order(&[3, 3, 1, 2, 1]), Ok(vec![1, 1, 2, 3, 3]));
+    }
+}
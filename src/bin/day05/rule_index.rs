@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use crate::rule_graph::RuleGraph;
+
+/**
+ * The rules as bitsets, built once so an update can be checked in a single pass. Each page named
+ * by a rule gets an index of its own, and row `index` has a bit set for every page that has to be
+ * printed before that page. Pages no rule names have no index, since nothing constrains them.
+ */
+pub struct RuleIndex {
+    indices: HashMap<usize, usize>,
+    predecessors: Vec<Vec<u64>>,
+    words: usize
+}
+
+impl RuleIndex {
+    /**
+     * Whether no page of the update comes after a page the rules say it must be printed before.
+     * Every page printed so far adds the pages that should have come before it to a running set,
+     * so each page is checked and added in time independent of the length of the update.
+     */
+    pub fn is_ordered(&self, update: &[usize]) -> bool {
+        let mut too_late = vec![0u64; self.words];
+        update.iter().all(|page| {
+            let Some(&index) = self.indices.get(page) else { return true };
+            if too_late[index / 64] & (1 << (index % 64)) != 0 {
+                return false;
+            }
+            too_late.iter_mut().zip(&self.predecessors[index]).for_each(|(word, predecessors)| *word |= predecessors);
+            true
+        })
+    }
+}
+
+impl From<&RuleGraph> for RuleIndex {
+    fn from(graph: &RuleGraph) -> Self {
+        let indices: HashMap<usize, usize> = graph.pages().into_iter().enumerate().map(|(index, page)| (page, index)).collect();
+        let words = indices.len().div_ceil(64);
+        let mut predecessors = vec![vec![0u64; words]; indices.len()];
+        graph.rules().into_iter().for_each(|(before, after)| {
+            let before = indices[&before];
+            predecessors[indices[&after]][before / 64] |= 1 << (before % 64);
+        });
+        Self { indices, predecessors, words }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_ordered() {
+        let index = RuleIndex::from(&RuleGraph::from_pairs(&[(1, 3), (1, 2)]));
+        assert!(index.is_ordered(&[1, 3, 2]));
+        assert!(!index.is_ordered(&[3, 1, 2]));
+        assert!(index.is_ordered(&[3, 2, 500, 1_000]));
+    }
+
+    #[test]
+    fn test_large_page_numbers() {
+        let index = RuleIndex::from(&RuleGraph::from_pairs(&[(1, 5_000_000), (usize::MAX, 1)]));
+        assert!(index.is_ordered(&[usize::MAX, 1, 5_000_000]));
+        assert!(!index.is_ordered(&[5_000_000, 7, 1]));
+        assert!(!index.is_ordered(&[1, usize::MAX]));
+    }
+
+    #[test]
+    fn test_is_ordered_matches_every_pair() {
+        // Pages past 64 so the rules span more than one word
+        let rules: Vec<(usize, usize)> = (0..150).flat_map(|before| {
+            (before + 1..150).filter(move |after| (before * 7 + after * 13) % 5 < 2).map(move |after| (before, after))
+        }).collect();
+        let graph = RuleGraph::from_pairs(&rules);
+        let index = RuleIndex::from(&graph);
+        let mut seed = 7usize;
+        for _ in 0..200 {
+            let update: Vec<usize> = (0..12).map(|_| {
+                seed = (seed * 1_103_515_245 + 12_345) % (1 << 31);
+                seed % 150
+            }).collect();
+            let pairwise = (0..update.len()).all(|first| {
+                (first + 1..update.len()).all(|second| !graph.has_rule(update[second], update[first]))
+            });
+            assert_eq!(index.is_ordered(&update), pairwise);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::fmt::{Display, Formatter, Result};
+use crate::rule_graph::RuleGraph;
+
+/// A rule `before|after` that an update breaks by printing `after` first
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct BrokenRule {
+    pub before: usize,
+    pub after: usize,
+    pub before_index: usize,
+    pub after_index: usize
+}
+
+impl Display for BrokenRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f, "{}|{} ({} at index {} is before {} at index {})",
+            self.before, self.after, self.after, self.after_index, self.before, self.before_index
+        )
+    }
+}
+
+/**
+ * Every rule an update breaks, and the fewest pages that would have to be moved to fix it.
+ * Moving a page can put it anywhere, so the pages that stay are the longest run of them already
+ * in the corrected order. `moves` is `None` when the rules don't give the update a single order.
+ */
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ViolationReport {
+    pub update: Vec<usize>,
+    pub broken: Vec<BrokenRule>,
+    pub moves: Option<usize>
+}
+
+impl ViolationReport {
+    pub fn new(graph: &RuleGraph, update: &[usize]) -> Self {
+        let broken = (0..update.len())
+            .flat_map(|after_index| (after_index + 1..update.len()).map(move |before_index| (after_index, before_index)))
+            .filter(|(after_index, before_index)| graph.has_rule(update[*before_index], update[*after_index]))
+            .map(|(after_index, before_index)| BrokenRule {
+                before: update[before_index],
+                after: update[after_index],
+                before_index,
+                after_index
+            })
+            .collect();
+        let moves = graph.order(update).ok().map(|order| {
+            let ranks: Vec<usize> = update.iter()
+                .map(|page| order.iter().position(|ordered| ordered == page).unwrap())
+                .collect();
+            update.len() - longest_increasing_run(&ranks)
+        });
+        Self { update: update.to_vec(), broken, moves }
+    }
+
+    /// How many pairs of pages are the wrong way round
+    pub fn inversions(&self) -> usize {
+        self.broken.len()
+    }
+}
+
+impl Display for ViolationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let update: Vec<String> = self.update.iter().map(|page| page.to_string()).collect();
+        let moves = self.moves.map_or("unknown".to_string(), |moves| moves.to_string());
+        writeln!(f, "{}: {} broken rules, {} moves to fix", update.join(","), self.inversions(), moves)?;
+        for rule in self.broken.iter() {
+            writeln!(f, "  {}", rule)?;
+        }
+        Ok(())
+    }
+}
+
+/// Length of the longest strictly increasing subsequence, by patience sorting
+fn longest_increasing_run(values: &[usize]) -> usize {
+    let mut tails: Vec<usize> = Vec::with_capacity(values.len());
+    for value in values {
+        let index = tails.partition_point(|tail| tail < value);
+        if index == tails.len() {
+            tails.push(*value);
+        } else {
+            tails[index] = *value;
+        }
+    }
+    tails.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_longest_increasing_run() {
+        assert_eq!(longest_increasing_run(&[]), 0);
+        assert_eq!(longest_increasing_run(&[0, 4, 1, 3, 2]), 3);
+        assert_eq!(longest_increasing_run(&[4, 3, 2, 1, 0]), 1);
+    }
+}
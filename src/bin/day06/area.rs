@@ -1,164 +1,341 @@
 use std::collections::{HashMap, HashSet};
+use crate::jump_table::JumpTable;
+use crate::patrol_rules::{PatrolRules, Turn};
 
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub struct Position {
-    x: i32,
-    y: i32
+    pub x: i32,
+    pub y: i32
 }
 
-#[derive(Clone)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub struct Guard {
-    position: Position,
-    direction: Direction,
-    visited: HashMap<Position, HashSet<Direction>>,
-    will_loop: bool
+    pub position: Position,
+    pub direction: Direction
 }
 
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
-pub enum Direction { Up, Down, Left, Right }
+pub enum Direction { Up, Down, Left, Right, UpRight, DownRight, DownLeft, UpLeft }
 
+/// One move of the guard, either a step forward or a turn on the spot, and which way it will turn next
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Step {
+    pub position: Position,
+    pub direction: Direction,
+    pub turned: bool,
+    pub next_turn: Turn
+}
+
+/// Every position the guard stood on and the directions it faced there, and whether it ended up going round in a loop
+pub struct Patrol {
+    pub visited: HashMap<Position, HashSet<Direction>>,
+    pub loops: bool
+}
+
+/// Guards patrol independently of each other, walking through one another without noticing
+#[derive(Clone)]
 pub struct Area {
     pub width: i32,
     pub height: i32,
-    pub guard: Guard,
+    pub guards: Vec<Guard>,
     pub obstacles: HashSet<Position>,
-    initial_guard: Guard
+    pub rules: PatrolRules
 }
 
 impl Area {
-    pub fn new(width: i32, height: i32, initial_guard: Guard, obstacles: HashSet<Position>) -> Self {
+    pub fn new(width: i32, height: i32, guards: Vec<Guard>, obstacles: HashSet<Position>) -> Self {
         Self {
             width,
             height,
-            guard: initial_guard.clone(),
+            guards,
             obstacles,
-            initial_guard
+            rules: PatrolRules::default()
         }
     }
 
-    pub fn how_many_distinct_positions(&mut self) -> usize {
-        self.traverse();
-        let count = self.guard.visited.len();
-        self.reset();
-        count
+    pub fn with_rules(self, rules: PatrolRules) -> Self {
+        Self { rules, ..self }
     }
 
-    pub fn how_many_unique_new_obstacles_cause_a_loop(&mut self) -> usize {
-        self.traverse();
-        let mut traveled_positions = self.guard.visited.clone();
-        traveled_positions.remove(&self.initial_guard.position);
-        self.reset();
+    pub fn how_many_distinct_positions(&self) -> usize {
+        self.visited().len()
+    }
 
-        traveled_positions.into_iter()
-            .map(|(position, _)| {
-                self.obstacles.insert(position);
-                let is_loop = self.traverse();
-                self.obstacles.remove(&position);
-                self.reset();
-                is_loop
+    /**
+        An obstacle counts if it traps any of the guards in a loop. It can't go where a guard
+        starts, even on another guard's path. Jump tables only know the puzzle's own rules, so
+        under any others each obstacle is tried by following the guard all over again
+     */
+    pub fn how_many_unique_new_obstacles_cause_a_loop(&self) -> usize {
+        let starts: HashSet<Position> = self.guards.iter().map(|guard| guard.position).collect();
+        let obstacles: Vec<Position> = if self.rules == PatrolRules::default() {
+            let table = JumpTable::new(self);
+            self.guards.iter().flat_map(|guard| table.loop_obstacles(self, guard)).collect()
+        } else {
+            let mut area = self.clone();
+            self.guards.iter().flat_map(|guard| area.loop_obstacles_by_replaying(guard)).collect()
+        };
+        obstacles.into_iter()
+            .filter(|position| !starts.contains(position))
+            .collect::<HashSet<Position>>()
+            .len()
+    }
+
+    /**
+        Tries an obstacle at each place on the guard's path by following its whole patrol again.
+        Slow, but it follows any rules, and the tests and benchmarks check the jump tables against it
+     */
+    pub(crate) fn loop_obstacles_by_replaying(&mut self, guard: &Guard) -> Vec<Position> {
+        let mut candidates: Vec<Position> = self.patrol(guard).visited.into_keys().collect();
+        candidates.retain(|position| *position != guard.position);
+        candidates.into_iter()
+            .filter(|position| {
+                self.obstacles.insert(*position);
+                let loops = self.patrol(guard).loops;
+                self.obstacles.remove(position);
+                loops
             })
-            .filter(|is_loop| *is_loop)
-            .count()
+            .collect()
     }
 
-    fn reset(&mut self) {
-        self.guard = self.initial_guard.clone();
+    /**
+        A guard's moves one at a time from where it starts, ending when it walks out of the
+        area. A guard stuck in a loop never leaves, so the steps go on forever
+     */
+    pub fn steps(&self, guard: &Guard) -> Steps<'_> {
+        Steps { area: self, guard: *guard, next_turn: self.rules.turn.first() }
     }
 
-    fn position_in_front(&self) -> Position {
-        match self.guard.direction {
-            Direction::Up    => Position { x: self.guard.position.x, y: self.guard.position.y - 1 },
-            Direction::Down  => Position { x: self.guard.position.x, y: self.guard.position.y + 1 },
-            Direction::Left  => Position { x: self.guard.position.x - 1, y: self.guard.position.y },
-            Direction::Right => Position { x: self.guard.position.x + 1, y: self.guard.position.y },
-        }
+    /**
+        Follow a guard until it leaves the area or comes back to somewhere it has already
+        been facing the same way and about to turn the same way, which means it is in a loop
+     */
+    pub fn patrol(&self, guard: &Guard) -> Patrol {
+        let mut visited: HashMap<Position, HashSet<Direction>> = HashMap::new();
+        visited.entry(guard.position).or_default().insert(guard.direction);
+        let mut states: HashSet<(Position, Direction, Turn)> = HashSet::new();
+        states.insert((guard.position, guard.direction, self.rules.turn.first()));
+        let loops = self.steps(guard).any(|step| {
+            visited.entry(step.position).or_default().insert(step.direction);
+            !states.insert((step.position, step.direction, step.next_turn))
+        });
+        Patrol { visited, loops }
+    }
+
+    /// The patrol of each guard, in the order they appear on the map
+    pub fn patrols(&self) -> Vec<Patrol> {
+        self.guards.iter().map(|guard| self.patrol(guard)).collect()
+    }
+
+    /// Every position visited by any of the guards
+    pub fn visited(&self) -> HashSet<Position> {
+        self.patrols().into_iter().flat_map(|patrol| patrol.visited.into_keys()).collect()
     }
 
-    fn position_in_bounds(&self, position: &Position) -> bool {
+    pub fn position_in_bounds(&self, position: &Position) -> bool {
         (0..self.width).contains(&position.x) && (0..self.height).contains(&position.y)
     }
+}
 
-    fn visit(&mut self) {
-        match self.guard.visited.get_mut(&self.guard.position) {
-            Some(directions) => {
-                self.guard.will_loop = !directions.insert(self.guard.direction);
-            }
-            None => {
-                let mut directions = HashSet::new();
-                directions.insert(self.guard.direction);
-                self.guard.visited.insert(self.guard.position, directions);
-            }
+impl Direction {
+    /// Every direction, turning clockwise an eighth at a time from up
+    const CLOCKWISE: [Direction; 8] = [
+        Direction::Up,
+        Direction::UpRight,
+        Direction::Right,
+        Direction::DownRight,
+        Direction::Down,
+        Direction::DownLeft,
+        Direction::Left,
+        Direction::UpLeft
+    ];
+
+    /// Turned a quarter of the way round, or an eighth if `diagonal` is set
+    pub fn turned(self, turn: Turn, diagonal: bool) -> Self {
+        let eighths = if diagonal { 1 } else { 2 };
+        let index = Self::CLOCKWISE.iter().position(|direction| *direction == self).unwrap();
+        match turn {
+            Turn::Right => Self::CLOCKWISE[(index + eighths) % 8],
+            Turn::Left => Self::CLOCKWISE[(index + 8 - eighths) % 8]
         }
     }
 
-    /**
-        Traverse through the area and return true if traversal ends due to a loop
-     */
-    fn traverse(&mut self) -> bool {
-        let mut new_position;
-        while {
-            new_position = self.position_in_front();
-            self.position_in_bounds(&new_position)
-        } {
-            if self.obstacles.contains(&new_position) {
-                self.guard.direction = match self.guard.direction {
-                    Direction::Up => Direction::Right,
-                    Direction::Down => Direction::Left,
-                    Direction::Left => Direction::Up,
-                    Direction::Right => Direction::Down,
-                };
-            } else {
-                self.guard.position = new_position;
-            }
-            self.visit();
-            if self.guard.will_loop {
-                return true;
-            }
+    fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::Up        => (0, -1),
+            Direction::Down      => (0, 1),
+            Direction::Left      => (-1, 0),
+            Direction::Right     => (1, 0),
+            Direction::UpRight   => (1, -1),
+            Direction::DownRight => (1, 1),
+            Direction::DownLeft  => (-1, 1),
+            Direction::UpLeft    => (-1, -1),
+        }
+    }
+}
+
+impl Guard {
+    pub fn position_in_front(&self) -> Position {
+        let (x_offset, y_offset) = self.direction.offset();
+        Position { x: self.position.x + x_offset, y: self.position.y + y_offset }
+    }
+}
+
+pub struct Steps<'a> {
+    area: &'a Area,
+    guard: Guard,
+    next_turn: Turn
+}
+
+impl Iterator for Steps<'_> {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut new_position = self.guard.position_in_front();
+        if self.area.rules.wrap {
+            new_position = Position { x: new_position.x.rem_euclid(self.area.width), y: new_position.y.rem_euclid(self.area.height) };
         }
-        false
+        if !self.area.position_in_bounds(&new_position) {
+            return None;
+        }
+        let turned = self.area.obstacles.contains(&new_position);
+        if turned {
+            self.guard.direction = self.guard.direction.turned(self.next_turn, self.area.rules.diagonal);
+            self.next_turn = self.area.rules.turn.after(self.next_turn);
+        } else {
+            self.guard.position = new_position;
+        }
+        Some(Step { position: self.guard.position, direction: self.guard.direction, turned, next_turn: self.next_turn })
     }
 }
 
 impl From<String> for Area {
+    /// The area is as wide as its longest line, and a trailing newline doesn't add an empty row
     fn from(value: String) -> Self {
         let mut obstacles = HashSet::new();
-        let mut guard = Guard { 
-            position: Position { x: 0, y: 0 }, 
-            direction: Direction::Up,
-            visited: HashMap::<Position, HashSet<Direction>>::new(),
-            will_loop: false
-        };
-        let mut x = 0;
-        let mut y = 0;
-        value.chars()
-            .for_each(|char| {
+        let mut guards = vec![];
+        let lines: Vec<&str> = value.lines().collect();
+        for (y, line) in lines.iter().enumerate() {
+            for (x, char) in line.chars().enumerate() {
+                let position = Position { x: x as i32, y: y as i32 };
                 match char {
                     '#' => {
-                        obstacles.insert(Position { x, y });
-                        x += 1;
-                    }
-                    '\n' => {
-                        x = 0;
-                        y += 1;
+                        obstacles.insert(position);
                     }
-                    '^' => {
-                        guard.position = Position { x, y };
-                        let mut directions = HashSet::new();
-                        directions.insert(guard.direction);
-                        guard.visited.insert(guard.position, directions);
-                        x += 1;
-                    }
-                    _ => {
-                        x += 1;
+                    '^' | '>' | 'v' | '<' => {
+                        let direction = match char {
+                            '^' => Direction::Up,
+                            '>' => Direction::Right,
+                            'v' => Direction::Down,
+                            _ => Direction::Left
+                        };
+                        guards.push(Guard { position, direction });
                     }
+                    _ => {}
                 }
-            });
-        Self::new(x, y + 1, guard, obstacles)
+            }
+        }
+        let width = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        Self::new(width as i32, lines.len() as i32, guards, obstacles)
     }
 }
 
 #[cfg(test)]
 mod test {
-    // use super::*;
+    use super::*;
+    use crate::patrol_rules::TurnRule;
 
-}
\ No newline at end of file
+    #[test]
+    fn test_steps() {
+        let area = Area::from(".#..\n....\n.^..".to_string());
+        let steps: Vec<Step> = area.steps(&area.guards[0]).collect();
+        let step = |x, y, direction, turned| Step { position: Position { x, y }, direction, turned, next_turn: Turn::Right };
+        assert_eq!(steps, vec![
+            step(1, 1, Direction::Up, false),
+            step(1, 1, Direction::Right, true),
+            step(2, 1, Direction::Right, false),
+            step(3, 1, Direction::Right, false)
+        ]);
+    }
+
+    #[test]
+    fn test_patrol_loop() {
+        let area = Area::from(".#..\n...#\n#^..\n..#.".to_string());
+        assert!(area.patrol(&area.guards[0]).loops);
+        // A guard in a loop keeps stepping until told to stop
+        assert_eq!(area.steps(&area.guards[0]).take(1_000).count(), 1_000);
+    }
+
+    #[test]
+    fn test_guard_glyphs() {
+        let area = Area::from("^.>\n...\n<.v".to_string());
+        let guard = |x, y, direction| Guard { position: Position { x, y }, direction };
+        assert_eq!(area.guards, vec![
+            guard(0, 0, Direction::Up),
+            guard(2, 0, Direction::Right),
+            guard(0, 2, Direction::Left),
+            guard(2, 2, Direction::Down)
+        ]);
+    }
+
+    #[test]
+    fn test_multiple_guards() {
+        let area = Area::from("....\n>..#\n..^.".to_string());
+        let visited = |patrol: &Patrol| {
+            let mut positions: Vec<(i32, i32)> = patrol.visited.keys().map(|position| (position.x, position.y)).collect();
+            positions.sort();
+            positions
+        };
+        let patrols = area.patrols();
+        assert_eq!(visited(&patrols[0]), vec![(0, 1), (1, 1), (2, 1), (2, 2)]);
+        assert_eq!(visited(&patrols[1]), vec![(2, 0), (2, 1), (2, 2)]);
+        assert_eq!(area.how_many_distinct_positions(), 5);
+    }
+
+    #[test]
+    fn test_turn_left() {
+        // The sample mirrored left to right, which a guard turning left walks the same way round
+        let sample = std::fs::read_to_string("./src/bin/day06/sample_input.txt").unwrap();
+        let mirrored: Vec<String> = sample.lines().map(|line| line.chars().rev().collect()).collect();
+        let rules = PatrolRules { turn: TurnRule::Left, ..PatrolRules::default() };
+        let area = Area::from(mirrored.join("\n")).with_rules(rules);
+        assert_eq!(area.how_many_distinct_positions(), 41);
+        assert_eq!(area.how_many_unique_new_obstacles_cause_a_loop(), 6);
+    }
+
+    #[test]
+    fn test_alternating_turns() {
+        // Turning right and then left keeps a boxed in guard facing between up and right
+        let area = Area::from(".#.\n#^#\n.#.".to_string())
+            .with_rules(PatrolRules { turn: TurnRule::Alternating, ..PatrolRules::default() });
+        let directions: Vec<Direction> = area.steps(&area.guards[0]).take(4).map(|step| step.direction).collect();
+        assert_eq!(directions, vec![Direction::Right, Direction::Up, Direction::Right, Direction::Up]);
+        assert!(area.patrol(&area.guards[0]).loops);
+    }
+
+    #[test]
+    fn test_wrap() {
+        let rules = PatrolRules { wrap: true, ..PatrolRules::default() };
+        let area = Area::from("...\n.^.\n...".to_string()).with_rules(rules);
+        let patrol = area.patrol(&area.guards[0]);
+        assert!(patrol.loops);
+        assert_eq!(patrol.visited.len(), 3);
+        // The guard never leaves, so an obstacle anywhere on its path just turns it into a row to loop round
+        assert_eq!(area.how_many_unique_new_obstacles_cause_a_loop(), 2);
+    }
+
+    #[test]
+    fn test_diagonal() {
+        let rules = PatrolRules { diagonal: true, ..PatrolRules::default() };
+        let area = Area::from(".#.\n...\n.^.".to_string()).with_rules(rules);
+        let steps: Vec<(i32, i32, Direction)> = area.steps(&area.guards[0])
+            .map(|step| (step.position.x, step.position.y, step.direction))
+            .collect();
+        assert_eq!(steps, vec![(1, 1, Direction::Up), (1, 1, Direction::UpRight), (2, 0, Direction::UpRight)]);
+        // Walled in on every side, including the corners, the guard turns on the spot for ever
+        let area = Area::from("###\n#^#\n###".to_string()).with_rules(rules);
+        let patrol = area.patrol(&area.guards[0]);
+        assert!(patrol.loops);
+        assert_eq!(patrol.visited[&Position { x: 1, y: 1 }].len(), 8);
+    }
+}
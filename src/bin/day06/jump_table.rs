@@ -0,0 +1,175 @@
+use crate::area::{Area, Direction, Guard, Position};
+
+/// Directions in the order the guard turns through them, so turning right is the next one
+const CLOCKWISE: [Direction; 4] = [Direction::Up, Direction::Right, Direction::Down, Direction::Left];
+
+fn index(direction: Direction) -> usize {
+    match direction {
+        Direction::Up => 0,
+        Direction::Right => 1,
+        Direction::Down => 2,
+        Direction::Left => 3,
+        _ => panic!("Jump tables only follow guards that turn a quarter at a time, not {:?}", direction)
+    }
+}
+
+/**
+ * For every cell and direction, where the guard would stop walking straight: the cell just
+ * before the next obstacle, or `None` if it would walk out of the area instead. With these the
+ * guard moves from turn to turn rather than a cell at a time, and a new obstacle only needs
+ * checking against the one stretch of each walk.
+ */
+pub struct JumpTable {
+    width: usize,
+    height: usize,
+    stops: [Vec<Option<usize>>; 4]
+}
+
+impl JumpTable {
+    pub fn new(area: &Area) -> Self {
+        let (width, height) = (area.width as usize, area.height as usize);
+        let blocked: Vec<bool> = (0..width * height)
+            .map(|cell| area.obstacles.contains(&Position { x: (cell % width) as i32, y: (cell / width) as i32 }))
+            .collect();
+        let mut stops: [Vec<Option<usize>>; 4] = std::array::from_fn(|_| vec![None; width * height]);
+        for direction in CLOCKWISE {
+            let table = &mut stops[index(direction)];
+            // Visit cells so the one in front has always been filled in already
+            let cells: Box<dyn Iterator<Item = usize>> = match direction {
+                Direction::Up | Direction::Left => Box::new(0..width * height),
+                _ => Box::new((0..width * height).rev())
+            };
+            for cell in cells {
+                let (x, y) = (cell % width, cell / width);
+                let in_front = match direction {
+                    Direction::Up => y.checked_sub(1).map(|y| y * width + x),
+                    Direction::Down => (y + 1 < height).then(|| cell + width),
+                    Direction::Left => x.checked_sub(1).map(|_| cell - 1),
+                    _ => (x + 1 < width).then(|| cell + 1)
+                };
+                table[cell] = in_front.and_then(|in_front| if blocked[in_front] { Some(cell) } else { table[in_front] });
+            }
+        }
+        Self { width, height, stops }
+    }
+
+    /**
+     * The places a single new obstacle would trap the guard in a loop, which can be anywhere on
+     * its path except where it starts. The guard's path up to the first time it reaches a place
+     * doesn't change, so each candidate is tried from just in front of it.
+     */
+    pub fn loop_obstacles(&self, area: &Area, start: &Guard) -> Vec<Position> {
+        if !area.position_in_bounds(&start.position) {
+            return vec![];
+        }
+        let mut reached = vec![false; self.width * self.height];
+        reached[self.cell(start.position)] = true;
+        // The directions the guard has faced in each cell, to stop if its own path is a loop
+        let mut walked = vec![0u8; self.width * self.height];
+        walked[self.cell(start.position)] = 1 << index(start.direction);
+        // Turns seen by each candidate's walk, marked with that candidate's number
+        let mut seen = vec![0u32; self.width * self.height * 4];
+        let mut candidate = 0;
+        let mut guard = *start;
+        let mut obstacles = vec![];
+        for step in area.steps(start) {
+            let facing = 1 << index(step.direction);
+            if walked[self.cell(step.position)] & facing != 0 {
+                break;
+            }
+            walked[self.cell(step.position)] |= facing;
+            if !step.turned && !reached[self.cell(step.position)] {
+                reached[self.cell(step.position)] = true;
+                candidate += 1;
+                if self.loops(guard, step.position, &mut seen, candidate) {
+                    obstacles.push(step.position);
+                }
+            }
+            guard = Guard { position: step.position, direction: step.direction };
+        }
+        obstacles
+    }
+
+    /// Whether a guard starting as `guard` walks in a loop once there is an obstacle at `obstacle`
+    fn loops(&self, guard: Guard, obstacle: Position, seen: &mut [u32], candidate: u32) -> bool {
+        let obstacle = (obstacle.x as usize, obstacle.y as usize);
+        let mut cell = self.cell(guard.position);
+        let mut direction = index(guard.direction);
+        loop {
+            let Some(stop) = self.stop(cell, direction, obstacle) else { return false };
+            direction = (direction + 1) % 4;
+            let state = stop * 4 + direction;
+            if seen[state] == candidate {
+                return true;
+            }
+            seen[state] = candidate;
+            cell = stop;
+        }
+    }
+
+    /// Where walking from `cell` stops, taking the new obstacle into account if it is in the way
+    fn stop(&self, cell: usize, direction: usize, (obstacle_x, obstacle_y): (usize, usize)) -> Option<usize> {
+        let stop = self.stops[direction][cell];
+        let (x, y) = (cell % self.width, cell / self.width);
+        let stop_at = |stop_x: usize, stop_y: usize| stop_y * self.width + stop_x;
+        let blocks = match CLOCKWISE[direction] {
+            Direction::Up => obstacle_x == x && obstacle_y < y && stop.is_none_or(|stop| obstacle_y >= stop / self.width),
+            Direction::Down => obstacle_x == x && obstacle_y > y && stop.is_none_or(|stop| obstacle_y <= stop / self.width),
+            Direction::Left => obstacle_y == y && obstacle_x < x && stop.is_none_or(|stop| obstacle_x >= stop % self.width),
+            _ => obstacle_y == y && obstacle_x > x && stop.is_none_or(|stop| obstacle_x <= stop % self.width)
+        };
+        if !blocks {
+            return stop;
+        }
+        Some(match CLOCKWISE[direction] {
+            Direction::Up => stop_at(obstacle_x, obstacle_y + 1),
+            Direction::Down => stop_at(obstacle_x, obstacle_y - 1),
+            Direction::Left => stop_at(obstacle_x + 1, obstacle_y),
+            _ => stop_at(obstacle_x - 1, obstacle_y)
+        })
+    }
+
+    fn cell(&self, position: Position) -> usize {
+        position.y as usize * self.width + position.x as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_guard_already_in_a_loop() {
+        let mut area = Area::from(".#..\n...#\n#^..\n..#.".to_string());
+        let guard = area.guards[0];
+        assert_eq!(area.how_many_unique_new_obstacles_cause_a_loop(), area.loop_obstacles_by_replaying(&guard).len());
+    }
+
+    #[test]
+    fn test_trailing_newline() {
+        let area = Area::from("..#.\n.^..\n....\n".to_string());
+        assert_eq!((area.width, area.height), (4, 3));
+        assert_eq!(area.how_many_distinct_positions(), 2);
+        assert_eq!(area.how_many_unique_new_obstacles_cause_a_loop(), 0);
+        let mut area = Area::from(".#..\n...#\n#^..\n..#.\n".to_string());
+        let guard = area.guards[0];
+        assert_eq!(area.how_many_unique_new_obstacles_cause_a_loop(), area.loop_obstacles_by_replaying(&guard).len());
+    }
+
+    #[test]
+    fn test_matches_replaying_the_patrol() {
+        let mut seed: u64 = 6;
+        for _ in 0..50 {
+            let map: String = (0..12).map(|y| {
+                (0..15).map(|x| {
+                    seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                    if (x, y) == (7, 6) { '^' } else if (seed >> 60) < 2 { '#' } else { '.' }
+                }).collect::<String>()
+            }).collect::<Vec<String>>().join("\n");
+            let mut area = Area::from(map);
+            let guard = area.guards[0];
+            let expected = area.loop_obstacles_by_replaying(&guard).len();
+            assert_eq!(area.how_many_unique_new_obstacles_cause_a_loop(), expected);
+        }
+    }
+}
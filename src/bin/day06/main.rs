@@ -1,14 +1,27 @@
 use std::fs::read_to_string;
 use area::Area;
+use patrol_rules::{PatrolRules, TurnRule};
 
 mod area;
+mod jump_table;
+mod patrol_rules;
 
 fn main() {
     // Most of the code is in `./src/bin/day6/area.rs`
-    let mut area = parse_input("./src/bin/day06/input.txt");
+    // `--turn=left|right|alternating`, `--wrap` and `--diagonal` change how the guards move
+    let flags: Vec<String> = std::env::args().skip(1).collect();
+    let rules = PatrolRules {
+        turn: flags.iter().find_map(|flag| flag.strip_prefix("--turn=")).map_or(TurnRule::Right, TurnRule::from),
+        wrap: flags.iter().any(|flag| flag == "--wrap"),
+        diagonal: flags.iter().any(|flag| flag == "--diagonal")
+    };
+    let area = parse_input("./src/bin/day06/input.txt").with_rules(rules);
     let unique_position_count = area.how_many_distinct_positions();
     // Part 1
     println!("Unique positions: {}", unique_position_count);
+    if area.patrols().iter().any(|patrol| patrol.loops) {
+        println!("A guard never leaves the area");
+    }
     // Part 2
     let unique_obstacles = area.how_many_unique_new_obstacles_cause_a_loop();
     println!("Unique obstacle count: {}", unique_obstacles);
@@ -28,7 +41,7 @@ mod test {
     fn test_sample_input_part_1() {
         let input = read_to_string("./src/bin/day06/sample_input.txt")
             .unwrap();
-        let mut area: Area = input.into();
+        let area: Area = input.into();
         assert_eq!(area.how_many_distinct_positions(), 41);
     }
 
@@ -36,7 +49,7 @@ mod test {
     fn test_sample_input_part_2() {
         let input = read_to_string("./src/bin/day06/sample_input.txt")
             .unwrap();
-        let mut area: Area = input.into();
+        let area: Area = input.into();
         assert_eq!(area.how_many_unique_new_obstacles_cause_a_loop(), 6);
     }
 }
\ No newline at end of file
@@ -0,0 +1,74 @@
+/// Which way a guard turns when it meets an obstacle
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Turn { Right, Left }
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TurnRule {
+    Right,
+    Left,
+    /// Right at the first obstacle, then left at the next and so on
+    Alternating
+}
+
+impl TurnRule {
+    pub fn first(self) -> Turn {
+        match self {
+            TurnRule::Left => Turn::Left,
+            TurnRule::Right | TurnRule::Alternating => Turn::Right
+        }
+    }
+
+    /// The turn to take at the obstacle after one where the guard turned `turn`
+    pub fn after(self, turn: Turn) -> Turn {
+        match (self, turn) {
+            (TurnRule::Alternating, Turn::Right) => Turn::Left,
+            (TurnRule::Alternating, Turn::Left) => Turn::Right,
+            _ => turn
+        }
+    }
+}
+
+impl From<&str> for TurnRule {
+    fn from(value: &str) -> Self {
+        match value {
+            "right" => TurnRule::Right,
+            "left" => TurnRule::Left,
+            "alternating" => TurnRule::Alternating,
+            _ => panic!("Invalid turn. {} is not right, left or alternating.", value)
+        }
+    }
+}
+
+/**
+ * How guards move. With `wrap` a guard walking off one edge comes back in at the opposite one,
+ * so it never leaves. With `diagonal` it turns an eighth of the way round instead of a quarter,
+ * so it can walk diagonally, and only the cell it steps into has to be free. The default is the
+ * puzzle's guard, turning right a quarter at a time until it leaves.
+ */
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct PatrolRules {
+    pub turn: TurnRule,
+    pub wrap: bool,
+    pub diagonal: bool
+}
+
+impl Default for PatrolRules {
+    fn default() -> Self {
+        Self { turn: TurnRule::Right, wrap: false, diagonal: false }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_turn_rule() {
+        assert_eq!(TurnRule::from("left").first(), Turn::Left);
+        assert_eq!(TurnRule::Left.after(Turn::Left), Turn::Left);
+        let alternating = TurnRule::from("alternating");
+        assert_eq!(alternating.first(), Turn::Right);
+        assert_eq!(alternating.after(alternating.first()), Turn::Left);
+        assert_eq!(alternating.after(Turn::Left), Turn::Right);
+    }
+}